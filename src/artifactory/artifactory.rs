@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Duration;
 use anyhow::{anyhow, bail, Context, ensure};
@@ -9,7 +9,8 @@ use indicatif::{ProgressBar, ProgressStyle};
 use futures_util::stream::StreamExt;
 use crate::artifactory::entry::Entry;
 use crate::resolver::{Dependency, PackageGet};
-use crate::types::{Arch, Distribution, OperatingSystem};
+use crate::types::Compression;
+use crate::utility::checksum::md5_file;
 
 pub struct Artifactory
 {
@@ -17,6 +18,7 @@ pub struct Artifactory
   pub url_format: String,
   pub username: Option<String>,
   pub token: Option<String>,
+  pub compression: Compression,
   url_ping: String,
   url_aql: String,
   url_api_format: String,
@@ -41,17 +43,19 @@ impl Artifactory
       .iter()
       .find(|x| x.name == name)
       .context("internal error: no such registry in config: {}. report this bug to the developers")?;
+    let pattern = url_pattern(&reg_data.pattern)
+      .context(format!("invalid package pattern of registry {}", name))?;
     let url_format = format!("{}{}{}/{}",
       reg_data.base_url,
       if reg_data.base_url.ends_with('/') { "" } else { "/" },
       reg_data.name,
-      reg_data.pattern
+      pattern
     );
     let url_api_format = format!("{}{}api/storage/{}/{}",
       reg_data.base_url,
       if reg_data.base_url.ends_with('/') { "" } else { "/" },
       reg_data.name,
-      reg_data.pattern
+      pattern
     );
     let url_ping = format!("{}{}{}",
       reg_data.base_url,
@@ -75,6 +79,7 @@ impl Artifactory
       url_aql: format!("{}{}api/search/aql", reg_data.base_url, if reg_data.base_url.ends_with('/') { "" } else { "/" }),
       username,
       token,
+      compression: reg_data.compression.unwrap_or_default(),
       config,
       available_packages: Vec::new()
     })
@@ -102,35 +107,37 @@ impl Artifactory
     Ok(())
   }
 
-  pub fn push(
-    &self,
-    path: &str,
-    packed_file: &str,
-    distribution: Distribution,
-    arch: Arch,
-    os: OperatingSystem,
-    force: bool
-  ) -> anyhow::Result<()>
+  /// Uploads `packed_file` as package identified by `dependency`. Packed file is removed afterwards in any case.
+  /// Returns `false` if different package already exists in registry and `force` is not set
+  pub fn push(&self, packed_file: &str, dependency: &Dependency, force: bool) -> anyhow::Result<bool>
   {
-    let manifest = crate::manifest::Manifest::from_directory(path)?;
+    let result = self.push_file(packed_file, dependency, force);
+    let _ = std::fs::remove_file(packed_file);
+    result
+  }
+
+  fn push_file(&self, packed_file: &str, dependency: &Dependency, force: bool) -> anyhow::Result<bool>
+  {
+    let compression = Compression::detect(Path::new(packed_file))?;
 
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(100));
     pb.set_message(format!("pushing {}@{}/{}/{}/{} to {}",
-      &manifest.this.name.bold().magenta(),
-      &manifest.this.version.to_string().bold().green(),
-      distribution.to_string().cyan().dimmed(),
-      arch.to_string().white().dimmed(),
-      os.to_string().white().dimmed(),
+      &dependency.name.bold().magenta(),
+      &dependency.version.to_string().bold().green(),
+      dependency.distribution_tag().cyan().dimmed(),
+      dependency.arch.to_string().white().dimmed(),
+      dependency.os.to_string().white().dimmed(),
       self.name.bold().bright_green()
     ));
 
     let mut fmt: HashMap<String, String> = HashMap::new();
-    fmt.insert("name".to_string(), manifest.this.name.clone());
-    fmt.insert("version".to_string(), manifest.this.version.clone().to_string());
-    fmt.insert("arch".to_string(), arch.to_string());
-    fmt.insert("platform".to_string(), os.to_string());
-    fmt.insert("dist".to_string(), distribution.to_string());
+    fmt.insert("name".to_string(), dependency.name.clone());
+    fmt.insert("version".to_string(), dependency.version.to_string());
+    fmt.insert("arch".to_string(), dependency.arch.to_string());
+    fmt.insert("platform".to_string(), dependency.os.to_string());
+    fmt.insert("dist".to_string(), dependency.distribution_tag());
+    fmt.insert("ext".to_string(), compression.extension().to_string());
 
     let url = strfmt::strfmt(self.url_format.as_str(), &fmt)
       .context("failed to format url")?;
@@ -142,21 +149,37 @@ impl Artifactory
       .send()?;
 
     if exists.status().is_success() {
+      let api_url = strfmt::strfmt(self.url_api_format.as_str(), &fmt)
+        .context("failed to format api url")?;
+      if self.remote_md5(&client, api_url.as_str()).ok() == Some(md5_file(Path::new(packed_file))?) {
+        pb.finish_and_clear();
+        println!("{} {}@{}/{}/{}/{} {} {}",
+          String::from("package").green().bold(),
+          dependency.name.bold().magenta(),
+          dependency.version.to_string().bold().green(),
+          dependency.distribution_tag().cyan(),
+          dependency.arch.to_string().white(),
+          dependency.os.to_string().white(),
+          String::from("is up to date in").green().bold(),
+          &self.name.bold().cyan()
+        );
+        return Ok(true);
+      }
+
       println!("{} {}@{}/{}/{}/{} {} {}",
         String::from("package").yellow().bold(),
-        manifest.this.name.bold().magenta(),
-        manifest.this.version.to_string().bold().green(),
-        distribution.to_string().cyan(),
-        arch.to_string().white(),
-        os.to_string().white(),
+        dependency.name.bold().magenta(),
+        dependency.version.to_string().bold().green(),
+        dependency.distribution_tag().cyan(),
+        dependency.arch.to_string().white(),
+        dependency.os.to_string().white(),
         String::from("already exists in").yellow().bold(),
         &self.name.bold().cyan()
       );
 
       if !force {
         pb.finish_and_clear();
-        println!("{}: use --force flag to push anyway", String::from("tip").cyan().bold());
-        return Ok(());
+        return Ok(false);
       } else {
         println!("{}", String::from("warning: overriding existing package").yellow().bold());
       }
@@ -171,18 +194,33 @@ impl Artifactory
     if !res.status().is_success() {
       bail!("failed to push package: {}", res.status());
     }
-    std::fs::remove_file(packed_file)?;
 
     pb.finish_with_message(format!("{} {}@{}/{}/{}/{} to {}",
       "successfully pushed".to_string().green().bold(),
-      &manifest.this.name.bold().magenta(),
-      &manifest.this.version.to_string().bold().green(),
-      distribution.to_string().cyan().dimmed(),
-      arch.to_string().white().dimmed(),
-      os.to_string().white().dimmed(),
+      &dependency.name.bold().magenta(),
+      &dependency.version.to_string().bold().green(),
+      dependency.distribution_tag().cyan().dimmed(),
+      dependency.arch.to_string().white().dimmed(),
+      dependency.os.to_string().white().dimmed(),
       &self.name.bold().cyan()
     ));
-    Ok(())
+    Ok(true)
+  }
+
+  fn remote_md5(&self, client: &reqwest::blocking::Client, api_url: &str) -> anyhow::Result<String>
+  {
+    let response = client
+      .get(api_url)
+      .basic_auth(self.username.as_ref().unwrap_or(&"guest".to_string()), self.token.clone())
+      .send()?;
+    ensure!(response.status().is_success(), "failed to query package info: {}", response.status());
+    let json: serde_json::Value = serde_json::from_str(response.text()?.as_str())?;
+    Ok(json
+      .get("checksums")
+      .and_then(|checksums| checksums.get("md5"))
+      .and_then(|checksum| checksum.as_str())
+      .context("checksum not found in api response")?
+      .to_string())
   }
 
   #[tokio::main]
@@ -212,7 +250,12 @@ impl Artifactory
     let mut packages: Vec<Entry> = Vec::new();
 
     for item in items.results {
-      packages.push(Entry::new(Dependency::from_package_name(&item.name)?, &self.url_format, &self.url_api_format)?);
+      packages.push(Entry::new(
+        Dependency::from_package_name(&item.name)?,
+        Compression::from_file_name(&item.name),
+        &self.url_format,
+        &self.url_api_format
+      )?);
     }
 
     self.available_packages = packages;
@@ -254,19 +297,20 @@ impl PackageGet for Artifactory
       &entry.dependency.version.to_string().green(),
       &entry.dependency.arch.to_string().dimmed(),
       &entry.dependency.os.to_string().dimmed(),
-      &entry.dependency.distribution.to_string().dimmed()
+      &entry.dependency.distribution_tag().dimmed()
     ));
 
     let target_path = self.config
       .directories
       .dirs
       .cache_dir()
-      .join(format!("{}-{}-{}-{}-{}.tar.gz",
+      .join(format!("{}-{}-{}-{}-{}.{}",
         entry.dependency.name,
         entry.dependency.version.to_string(),
         entry.dependency.arch.to_string(),
         entry.dependency.os.to_string(),
-        entry.dependency.distribution.to_string()
+        entry.dependency.distribution_tag(),
+        entry.compression.extension()
       ));
     let mut downloaded: u64 = 0;
     let mut stream = result.bytes_stream();
@@ -331,4 +375,40 @@ impl PackageGet for Artifactory
     pb.finish_and_clear();
     Ok(entry.context("package not found")?.dependency.clone())
   }
-}
\ No newline at end of file
+}
+
+/// Replaces archive extension at the end of registry pattern with `{ext}` placeholder, so that file name always
+/// follows package compression. Patterns with neither are rejected
+fn url_pattern(pattern: &str) -> anyhow::Result<String>
+{
+  if pattern.contains("{ext}") {
+    return Ok(pattern.to_string());
+  }
+  [Compression::Gzip, Compression::Zstd, Compression::Xz]
+    .iter()
+    .find_map(|x| pattern.strip_suffix(format!(".{}", x.extension()).as_str()))
+    .map(|x| format!("{}.{{ext}}", x))
+    .context(format!("pattern {} must contain {{ext}} placeholder or end with archive extension", pattern))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_url_pattern_follows_compression() {
+    let pattern = url_pattern("packages/{name}/{name}-{version}-{arch}-{platform}-{dist}.tar.zst").unwrap();
+    assert_eq!(pattern, "packages/{name}/{name}-{version}-{arch}-{platform}-{dist}.{ext}");
+    let entry = Entry::new(
+      Dependency::from_package_name("fmt-1.0.0-x86_64-linux-static.tar.xz").unwrap(),
+      Compression::Xz,
+      pattern.as_str(),
+      pattern.as_str()
+    ).unwrap();
+    assert_eq!(entry.url, "packages/fmt/fmt-1.0.0-x86_64-linux-static.tar.xz");
+
+    assert_eq!(url_pattern("{name}/{version}/{name}.{ext}").unwrap(), "{name}/{version}/{name}.{ext}");
+    assert!(url_pattern("{name}/{version}/{name}.zip").is_err());
+  }
+}
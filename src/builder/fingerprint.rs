@@ -0,0 +1,94 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::names::{BUILD_METADATA_FILE, EXTENSIONS_DIRECTORY};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BuildFingerprint
+{
+  pub toolchain: String,
+  pub compiler: String,
+  pub build_type: String,
+  pub recipe: String
+}
+
+#[derive(Serialize)]
+struct BuildMetadata<'a>
+{
+  digest: String,
+
+  #[serde(flatten)]
+  fingerprint: &'a BuildFingerprint
+}
+
+impl BuildFingerprint
+{
+  pub fn new(toolchain: String, build_type: String, recipe: &[u8]) -> Self
+  {
+    Self
+    {
+      toolchain,
+      compiler: Self::detect_compiler(),
+      build_type,
+      recipe: format!("{:x}", md5::compute(recipe))
+    }
+  }
+
+  pub fn digest(&self) -> String
+  {
+    format!("{:x}", md5::compute(format!("{}\n{}\n{}\n{}",
+      self.toolchain,
+      self.compiler,
+      self.build_type,
+      self.recipe
+    )))
+  }
+
+  pub fn save(&self, export_directory: &str) -> anyhow::Result<()>
+  {
+    let directory = Path::new(export_directory).join(EXTENSIONS_DIRECTORY);
+    std::fs::create_dir_all(&directory)?;
+    std::fs::write(
+      directory.join(BUILD_METADATA_FILE),
+      toml::to_string(&BuildMetadata { digest: self.digest(), fingerprint: self })?
+    )?;
+    Ok(())
+  }
+
+  fn detect_compiler() -> String
+  {
+    let compiler = std::env::var("CXX").unwrap_or(String::from("c++"));
+    std::process::Command::new(&compiler)
+      .arg("--version")
+      .output()
+      .ok()
+      .filter(|x| x.status.success())
+      .and_then(|x| String::from_utf8_lossy(&x.stdout)
+        .lines()
+        .next()
+        .map(|line| format!("{} ({})", compiler, line.trim()))
+      )
+      .unwrap_or(format!("{} (unknown)", compiler))
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  fn fingerprint(build_type: &str) -> BuildFingerprint
+  {
+    BuildFingerprint {
+      toolchain: String::from("cmake"),
+      compiler: String::from("c++ (gcc 13.2.1)"),
+      build_type: build_type.to_string(),
+      recipe: String::from("d41d8cd98f00b204e9800998ecf8427e")
+    }
+  }
+
+  #[test]
+  fn test_digest() {
+    assert_eq!(fingerprint("Release").digest(), fingerprint("Release").digest());
+    assert_ne!(fingerprint("Release").digest(), fingerprint("Debug").digest());
+  }
+}
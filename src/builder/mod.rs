@@ -1,3 +1,9 @@
 mod recipe;
+mod fingerprint;
+mod profile;
+mod source;
 
-pub use recipe::Recipe;
\ No newline at end of file
+pub use recipe::{HooksSection, Recipe};
+pub use fingerprint::BuildFingerprint;
+pub use profile::BuildProfile;
+pub use source::SourceSection;
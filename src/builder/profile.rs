@@ -0,0 +1,140 @@
+use anyhow::{bail, ensure};
+use crate::core;
+
+pub const DEFAULT_PROFILE: &str = "release";
+
+/// Named set of build settings applied to every package built from sources
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildProfile
+{
+  pub name: String,
+
+  /// CMake build type: `Debug`, `Release`, `RelWithDebInfo` or `MinSizeRel`
+  pub build_type: String,
+  pub cflags: Option<String>,
+  pub cxxflags: Option<String>,
+  pub ldflags: Option<String>
+}
+
+impl Default for BuildProfile
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      name: String::from(DEFAULT_PROFILE),
+      build_type: String::from("Release"),
+      cflags: None,
+      cxxflags: None,
+      ldflags: None
+    }
+  }
+}
+
+impl BuildProfile
+{
+  /// Looks up profile in config, falling back to built-in `debug`, `release`, `relwithdebinfo` and `minsizerel`
+  pub fn new(name: &str, config: &core::Config) -> anyhow::Result<Self>
+  {
+    let name = name.to_lowercase();
+    ensure!(
+      !name.is_empty() && name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_'),
+      "invalid profile name: {} (only letters, digits and underscores are allowed)", name
+    );
+    if let Some(x) = config.profiles.get(&name) {
+      return Ok(Self::from_config(name, x));
+    }
+    let build_type = match name.as_str() {
+      "debug" => "Debug",
+      "release" => "Release",
+      "relwithdebinfo" => "RelWithDebInfo",
+      "minsizerel" => "MinSizeRel",
+      _ => bail!("unknown build profile: {}", name)
+    };
+    Ok(Self
+    {
+      name,
+      build_type: build_type.to_string(),
+      cflags: None,
+      cxxflags: None,
+      ldflags: None
+    })
+  }
+
+  fn from_config(name: String, config: &core::ProfileConfig) -> Self
+  {
+    Self
+    {
+      name,
+      build_type: config.build_type.clone().unwrap_or(String::from("Release")),
+      cflags: config.cflags.clone(),
+      cxxflags: config.cxxflags.clone(),
+      ldflags: config.ldflags.clone()
+    }
+  }
+
+  /// Profile as recorded in package identity. Default profile is omitted
+  pub fn tag(&self) -> Option<String>
+  {
+    match self.name == DEFAULT_PROFILE {
+      true => None,
+      false => Some(self.name.clone())
+    }
+  }
+
+  /// Compiler flags for build systems without a notion of build type
+  pub fn compiler_flags(&self) -> String
+  {
+    match self.build_type.to_lowercase().as_str() {
+      "debug" => "-O0 -g",
+      "relwithdebinfo" => "-O2 -g",
+      "minsizerel" => "-Os",
+      _ => "-O2"
+    }.to_string()
+  }
+
+  /// `CFLAGS`, `CXXFLAGS` and `LDFLAGS` set by profile
+  pub fn environment(&self) -> Vec<(String, String)>
+  {
+    [("CFLAGS", &self.cflags), ("CXXFLAGS", &self.cxxflags), ("LDFLAGS", &self.ldflags)]
+      .into_iter()
+      .filter_map(|(name, value)| value.as_ref().map(|x| (name.to_string(), x.clone())))
+      .collect()
+  }
+
+  /// Describes profile for build fingerprint
+  pub fn configuration(&self) -> String
+  {
+    format!("{} {} cflags={} cxxflags={} ldflags={}",
+      self.name,
+      self.build_type,
+      self.cflags.as_deref().unwrap_or_default(),
+      self.cxxflags.as_deref().unwrap_or_default(),
+      self.ldflags.as_deref().unwrap_or_default()
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_new() {
+    let mut config = core::Config::default();
+    config.profiles.insert(String::from("asan"), core::ProfileConfig {
+      build_type: Some(String::from("Debug")),
+      cflags: Some(String::from("-fsanitize=address")),
+      ..Default::default()
+    });
+    assert_eq!(BuildProfile::new("Release", &config).unwrap(), BuildProfile::default());
+    assert_eq!(BuildProfile::new("release", &config).unwrap().tag(), None);
+    assert_eq!(BuildProfile::new("relwithdebinfo", &config).unwrap().build_type, "RelWithDebInfo");
+    let asan = BuildProfile::new("asan", &config).unwrap();
+    assert_eq!(asan.tag(), Some(String::from("asan")));
+    assert_eq!(asan.environment(), vec![(String::from("CFLAGS"), String::from("-fsanitize=address"))]);
+    assert!(BuildProfile::new("fast", &config).is_err());
+    assert!(BuildProfile::new("my-profile", &config).is_err());
+  }
+}
@@ -1,8 +1,14 @@
-use std::collections::HashMap;
-use anyhow::{anyhow, Context};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::path::Path;
+use std::process::Command;
+use anyhow::{anyhow, ensure, Context};
 use serde::{Deserialize, Serialize};
+use crate::builder::SourceSection;
+use crate::cache::Cache;
 use crate::names::{EXTENSIONS_DIRECTORY, RECIPE_FILE};
-use crate::types::Distribution;
+use crate::toolchains::BuildLog;
+use crate::types::{deserialize_definitions, deserialize_optional_definitions, Arch, CMakeDefinition, Distribution, OperatingSystem};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Recipe
@@ -12,6 +18,26 @@ pub struct Recipe
 
   #[serde(rename = "shared")]
   pub shared_toolchain: Option<Toolchain>,
+
+  /// Upstream sources. If omitted, sources are expected to be packed with the recipe
+  pub source: Option<SourceSection>,
+
+  /// Patches relative to package root, applied in order after fetching sources
+  pub patches: Option<Vec<String>>,
+
+  /// Environment variables for every build step and hook, on top of toolchain environment
+  pub env: Option<HashMap<String, String>>,
+
+  pub hooks: Option<HooksSection>,
+}
+
+/// Shell commands run around toolchain steps, see `toolchains::Hooks` for available variables
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HooksSection
+{
+  pub pre_configure: Option<Vec<String>>,
+  pub post_build: Option<Vec<String>>,
+  pub post_install: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,6 +50,9 @@ pub struct Toolchain
 pub struct ToolchainSection
 {
   pub cmake: Option<CMakeSection>,
+  pub meson: Option<MesonSection>,
+  pub autotools: Option<AutotoolsSection>,
+  pub make: Option<MakeSection>,
   pub shell: Option<Vec<String>>
 }
 
@@ -31,7 +60,85 @@ pub struct ToolchainSection
 pub struct CMakeSection
 {
   pub generator: Option<String>,
-  pub definitions: Option<HashMap<String, String>>,
+
+  #[serde(default, deserialize_with = "deserialize_optional_definitions")]
+  pub definitions: Option<HashMap<String, CMakeDefinition>>,
+
+  /// Definitions applied on top of `definitions` when target matches, in order of appearance
+  pub overrides: Option<Vec<CMakeOverride>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CMakeOverride
+{
+  pub os: Option<String>,
+  pub arch: Option<String>,
+  pub distribution: Option<String>,
+
+  #[serde(deserialize_with = "deserialize_definitions")]
+  pub definitions: HashMap<String, CMakeDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MesonSection
+{
+  pub options: Option<HashMap<String, String>>,
+  pub backend: Option<String>,
+
+  /// Path to cross file relative to package root. Generated from target arch and os if omitted
+  pub cross_file: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AutotoolsSection
+{
+  pub configure_args: Option<Vec<String>>,
+
+  /// Run `autoreconf -fi` before configure, for packages shipped without generated `configure`
+  #[serde(default)]
+  pub autoreconf: bool,
+
+  pub make_targets: Option<Vec<String>>,
+  pub env: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MakeSection
+{
+  pub targets: Option<Vec<String>>,
+
+  /// Variables passed on make command line, e.g. `CFLAGS: -O2`
+  pub variables: Option<HashMap<String, String>>,
+  pub env: Option<HashMap<String, String>>,
+}
+
+impl CMakeSection
+{
+  /// Definitions for given target with all matching overrides applied
+  pub fn definitions_for(&self, os: OperatingSystem, arch: Arch, distribution: Distribution) -> anyhow::Result<BTreeMap<String, CMakeDefinition>>
+  {
+    let mut definitions: BTreeMap<String, CMakeDefinition> = self.definitions
+      .clone()
+      .unwrap_or_default()
+      .into_iter()
+      .collect();
+    for x in self.overrides.iter().flatten() {
+      if x.matches(os, arch, distribution)? {
+        definitions.extend(x.definitions.clone());
+      }
+    }
+    Ok(definitions)
+  }
+}
+
+impl CMakeOverride
+{
+  pub fn matches(&self, os: OperatingSystem, arch: Arch, distribution: Distribution) -> anyhow::Result<bool>
+  {
+    Ok(self.os.as_deref().map_or(Ok(true), |x| OperatingSystem::from_str(x).map(|x| x == os))?
+      && self.arch.as_deref().map_or(Ok(true), |x| Arch::from_str(x).map(|x| x == arch))?
+      && self.distribution.as_deref().map_or(Ok(true), |x| Distribution::from_str(x).map(|x| x == distribution))?)
+  }
 }
 
 impl Recipe
@@ -48,6 +155,37 @@ impl Recipe
     )?)?)
   }
 
+  /// Parses raw recipe file contents, as returned by `read_from_tarball`
+  pub fn from_slice(data: &[u8]) -> anyhow::Result<Self>
+  {
+    Ok(serde_yaml::from_slice(data)?)
+  }
+
+  /// Returns raw recipe file contents from tarball
+  pub fn read_from_tarball(tar_path: &str) -> anyhow::Result<Vec<u8>>
+  {
+    crate::pack::read_entry(tar_path, format!("{}/{}", EXTENSIONS_DIRECTORY, RECIPE_FILE).as_str())?
+      .context(format!("recipe file not found in {}", tar_path))
+  }
+
+  /// Fetches upstream sources into package root and applies patches
+  pub fn prepare_sources(&self, root: &Path, cache: &Cache, log: &BuildLog) -> anyhow::Result<()>
+  {
+    if let Some(source) = &self.source {
+      source.fetch(root, cache, log)?;
+    }
+    for x in self.patches.iter().flatten() {
+      let patch = root.join(x);
+      ensure!(patch.is_file(), "patch {} not found", patch.display());
+      log.run(Command::new("git")
+        .current_dir(root)
+        .arg("apply")
+        .arg("--verbose")
+        .arg(&patch), format!("patch {}", x).as_str())?;
+    }
+    Ok(())
+  }
+
   pub fn extract_toolchain(&self, distribution: Distribution) -> anyhow::Result<ToolchainSection>
   {
     match distribution {
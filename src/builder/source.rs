@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Serialize};
+use crate::cache::Cache;
+use crate::toolchains::BuildLog;
+use crate::utility::checksum::sha256_file;
+
+/// Upstream sources of package, fetched into package root before build.
+/// Exactly one of `url`, `path` or `git` must be given
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourceSection
+{
+  /// Archive to download, e.g. `https://zlib.net/zlib-1.3.1.tar.gz`. Tarballs compressed with gzip, zstd or xz are supported
+  pub url: Option<String>,
+
+  /// Archive relative to package root
+  pub path: Option<String>,
+
+  /// Expected digest of archive. Required for `url` and `path`
+  pub sha256: Option<String>,
+
+  /// Number of leading path components to drop from archive entries
+  #[serde(default)]
+  pub strip_components: usize,
+
+  pub git: Option<String>,
+
+  /// Commit to check out. Required for `git`
+  pub commit: Option<String>,
+}
+
+impl SourceSection
+{
+  /// Places sources into `root`. Downloaded archives are cached by digest
+  pub fn fetch(&self, root: &Path, cache: &Cache, log: &BuildLog) -> anyhow::Result<()>
+  {
+    match (&self.url, &self.path, &self.git) {
+      (Some(url), None, None) => {
+        let archive = self.download(url, cache)?;
+        self.extract(&archive, root)
+      },
+      (None, Some(path), None) => {
+        let archive = root.join(path);
+        ensure!(archive.is_file(), "source archive {} not found", archive.display());
+        self.verify(&archive)?;
+        self.extract(&archive, root)
+      },
+      (None, None, Some(git)) => self.checkout(git, root, log),
+      _ => bail!("source section must have exactly one of url, path or git")
+    }
+  }
+
+  fn download(&self, url: &str, cache: &Cache) -> anyhow::Result<PathBuf>
+  {
+    let digest = self.digest()?;
+    if let Some(x) = cache.get_source(&digest) {
+      if sha256_file(&x)? == digest {
+        return Ok(x);
+      }
+    }
+
+    let name = url
+      .split(['?', '#'])
+      .next()
+      .and_then(|x| x.rsplit('/').next())
+      .filter(|x| !x.is_empty())
+      .context(format!("failed to get archive name from url {}", url))?;
+    let response = reqwest::blocking::Client::new()
+      .get(url)
+      .send()?;
+    ensure!(response.status().is_success(), "failed to download {}: {}", url, response.status());
+    let directory = tempfile::tempdir()?;
+    let archive = directory.path().join(name);
+    std::fs::write(&archive, response.bytes()?)?;
+    self.verify(&archive)?;
+    cache.put_source(&archive, name, &digest)
+  }
+
+  fn extract(&self, archive: &Path, root: &Path) -> anyhow::Result<()>
+  {
+    crate::pack::unpack_stripped(
+      archive.to_str().context("failed to convert path to string")?,
+      root.to_str().context("failed to convert path to string")?,
+      self.strip_components
+    ).context(format!("failed to extract {}", archive.display()))
+  }
+
+  fn checkout(&self, url: &str, root: &Path, log: &BuildLog) -> anyhow::Result<()>
+  {
+    let commit = self.commit
+      .as_ref()
+      .context(format!("commit is required for git source {}", url))?;
+    let directory = tempfile::tempdir()?;
+    log.run(Command::new("git")
+      .arg("clone")
+      .arg("--no-checkout")
+      .arg(url)
+      .arg(directory.path()), "git clone")?;
+    log.run(Command::new("git")
+      .arg("-C")
+      .arg(directory.path())
+      .arg("checkout")
+      .arg("--detach")
+      .arg(commit), "git checkout")?;
+    fs_extra::dir::copy(
+      directory.path(),
+      root,
+      &fs_extra::dir::CopyOptions::new()
+        .content_only(true)
+        .overwrite(true)
+    ).context(format!("failed to copy sources of {} into {}", url, root.display()))?;
+    Ok(())
+  }
+
+  fn digest(&self) -> anyhow::Result<String>
+  {
+    Ok(self.sha256
+      .as_ref()
+      .context("sha256 is required for archive sources")?
+      .to_lowercase())
+  }
+
+  fn verify(&self, archive: &Path) -> anyhow::Result<()>
+  {
+    let expected = self.digest()?;
+    let actual = sha256_file(archive)?;
+    ensure!(actual == expected, "sha256 mismatch for {}: expected {}, got {}", archive.display(), expected, actual);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_archive_is_verified_and_stripped() {
+    let root = tempfile::tempdir().unwrap();
+    let upstream = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(upstream.path().join("zlib-1.3/src")).unwrap();
+    std::fs::write(upstream.path().join("zlib-1.3/src/zlib.h"), b"#pragma once").unwrap();
+    let archive = root.path().join("zlib.tar.gz");
+    crate::pack::pack(upstream.path().to_str().unwrap(), archive.to_str().unwrap(), crate::types::Compression::Gzip).unwrap();
+
+    let mut source = SourceSection {
+      url: None,
+      path: Some(String::from("zlib.tar.gz")),
+      sha256: Some(String::from("0000")),
+      strip_components: 1,
+      git: None,
+      commit: None
+    };
+    assert!(source.verify(&archive).is_err());
+
+    source.sha256 = Some(sha256_file(&archive).unwrap().to_uppercase());
+    source.verify(&archive).unwrap();
+    source.extract(&archive, root.path()).unwrap();
+    assert!(root.path().join("src/zlib.h").is_file());
+  }
+}
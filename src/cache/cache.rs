@@ -1,11 +1,14 @@
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use std::time::Duration;
-use anyhow::{anyhow, bail, Context};
+use anyhow::{anyhow, bail, ensure, Context};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressFinish};
+use crate::builder::BuildFingerprint;
 use crate::core;
+use crate::cache::PackageIndex;
+use crate::names::{BUILT_FOLDER, PACKAGE_INDEX_FILE, SOURCES_FOLDER};
 use crate::resolver::{Dependency, PackageGet};
 
 pub struct Cache
@@ -42,14 +45,139 @@ impl Cache
     Ok(())
   }
 
-  pub fn put(&self, tarball_path: &str) -> anyhow::Result<()>
+  pub fn put_built(&self, tarball_path: &str, fingerprint: &BuildFingerprint) -> anyhow::Result<PathBuf>
   {
     let path = PathBuf::from(tarball_path);
-    std::fs::copy(tarball_path, &self.path.join(
-      path.file_name().unwrap().to_str().unwrap()
-    ))?;
+    let directory = self.path
+      .join(BUILT_FOLDER)
+      .join(fingerprint.digest());
+    std::fs::create_dir_all(&directory)?;
+    let target_path = directory.join(path.file_name().context("invalid tarball path")?);
+    std::fs::copy(tarball_path, &target_path)?;
+    Ok(target_path)
+  }
+
+  pub fn get_built(&self, dependency: &Dependency, fingerprint: &BuildFingerprint) -> anyhow::Result<PathBuf>
+  {
+    let directory = self.path
+      .join(BUILT_FOLDER)
+      .join(fingerprint.digest());
+    Self::packages_in(&directory)?
+      .into_iter()
+      .find(|x| x.1 == *dependency)
+      .map(|x| x.0)
+      .context(format!("no such package built with fingerprint {}: {}", fingerprint.digest(), dependency))
+  }
+
+  /// Stores upstream source archive under its sha256 digest, keeping original file name
+  pub fn put_source(&self, archive: &Path, name: &str, digest: &str) -> anyhow::Result<PathBuf>
+  {
+    let directory = self.path
+      .join(SOURCES_FOLDER)
+      .join(digest);
+    std::fs::create_dir_all(&directory)?;
+    let target_path = directory.join(name);
+    std::fs::copy(archive, &target_path)?;
+    Ok(target_path)
+  }
+
+  pub fn get_source(&self, digest: &str) -> Option<PathBuf>
+  {
+    std::fs::read_dir(self.path.join(SOURCES_FOLDER).join(digest))
+      .ok()?
+      .filter_map(|x| x.ok())
+      .map(|x| x.path())
+      .find(|x| x.is_file())
+  }
+
+  /// Returns all packages built from sources for given dependency, regardless of build fingerprint
+  pub fn built_for(&self, dependency: &Dependency) -> anyhow::Result<Vec<PathBuf>>
+  {
+    let directory = self.path.join(BUILT_FOLDER);
+    if !directory.exists() {
+      return Ok(Vec::new());
+    }
+    let mut packages = Vec::new();
+    for x in std::fs::read_dir(directory)? {
+      packages.extend(Self::packages_in(&x?.path())?
+        .into_iter()
+        .filter(|x| x.1 == *dependency)
+        .map(|x| x.0)
+      );
+    }
+    Ok(packages)
+  }
+
+  pub fn export(&self, tarballs: &[PathBuf], output: &str) -> anyhow::Result<()>
+  {
+    let pb = ProgressBar::new_spinner();
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb.set_message(format!("exporting {} packages", tarballs.len()));
+
+    let mut index = PackageIndex::default();
+    let mut bundle = tar::Builder::new(std::fs::File::create(output)?);
+    for x in tarballs {
+      let relative = x
+        .strip_prefix(&self.path)
+        .context(format!("package {} is not located in cache", x.display()))?;
+      index.add(&self.path, relative)?;
+      bundle.append_path_with_name(x, relative)?;
+    }
+    let index = toml::to_string(&index)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(index.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    bundle.append_data(&mut header, PACKAGE_INDEX_FILE, index.as_bytes())?;
+    bundle.finish()?;
+
+    pb.finish_and_clear();
     Ok(())
   }
+
+  pub fn import(&self, bundle: &str) -> anyhow::Result<usize>
+  {
+    let pb = ProgressBar::new_spinner();
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb.set_message(format!("importing {}", bundle));
+
+    let tmp_dir = tempfile::tempdir()?;
+    crate::pack::unpack_tar(bundle, tmp_dir.path().to_str().context("failed to convert tmpdir path to str")?)?;
+    let index = PackageIndex::from_directory(tmp_dir.path())?;
+    for x in &index.packages {
+      ensure!(
+        Path::new(&x.file).components().all(|c| matches!(c, Component::Normal(_))),
+        "invalid package path in bundle: {}", x.file
+      );
+      PackageIndex::verify(tmp_dir.path(), x)?;
+    }
+    for x in &index.packages {
+      let target_path = self.path.join(&x.file);
+      std::fs::create_dir_all(target_path.parent().context("invalid package path")?)?;
+      std::fs::copy(tmp_dir.path().join(&x.file), target_path)?;
+    }
+
+    pb.finish_and_clear();
+    Ok(index.packages.len())
+  }
+
+  fn packages_in(directory: &Path) -> anyhow::Result<Vec<(PathBuf, Dependency)>>
+  {
+    if !directory.exists() {
+      return Ok(Vec::new());
+    }
+    let mut packages = Vec::new();
+    for x in std::fs::read_dir(directory)? {
+      let path = x?.path();
+      if !path.is_file() {
+        continue;
+      }
+      if let Ok(dependency) = Dependency::from_std_path(&path) {
+        packages.push((path, dependency));
+      }
+    }
+    Ok(packages)
+  }
 }
 
 impl PackageGet for Cache
@@ -59,9 +187,7 @@ impl PackageGet for Cache
   {
     let dep = self.latest_satisfied(dependency, allow_sources)?;
 
-    for x in std::fs::read_dir(&self.path)? {
-      let path = x?.path();
-      let found = Dependency::from_package_name(path.file_name().unwrap().to_str().unwrap())?;
+    for (path, found) in Self::packages_in(&self.path)? {
       if found == dep { return Ok(path); }
     }
     Err(anyhow!("no such package in cache: {}", dep))
@@ -73,9 +199,7 @@ impl PackageGet for Cache
       .with_message(format!("searching for {}", dependency))
       .with_finish(ProgressFinish::AndClear);
     let mut valid_versions = Vec::new();
-    for x in std::fs::read_dir(&self.path)? {
-      let path = x?.path();
-      let d = Dependency::from_package_name(path.file_name().unwrap().to_str().unwrap())?;
+    for (_, d) in Self::packages_in(&self.path)? {
       if d.ranged_compare(dependency) {
         valid_versions.push(d);
       }
@@ -92,9 +216,7 @@ impl PackageGet for Cache
     if found.is_none() && allow_sources {
       let src = dependency.as_sources_dependency();
       let mut valid_versions = Vec::new();
-      for x in std::fs::read_dir(&self.path)? {
-        let path = x?.path();
-        let found = Dependency::from_package_name(path.file_name().unwrap().to_str().unwrap())?;
+      for (_, found) in Self::packages_in(&self.path)? {
         if found.ranged_compare(&src) {
           valid_versions.push(found);
         }
@@ -0,0 +1,94 @@
+use std::path::Path;
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+use crate::names::PACKAGE_INDEX_FILE;
+use crate::resolver::Dependency;
+use crate::utility::checksum::md5_file;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PackageIndex
+{
+  #[serde(default, rename = "package")]
+  pub packages: Vec<PackageIndexEntry>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackageIndexEntry
+{
+  pub file: String,
+  pub md5: String
+}
+
+impl PackageIndexEntry
+{
+  pub fn dependency(&self) -> anyhow::Result<Dependency>
+  {
+    Dependency::from_std_path(Path::new(&self.file))
+  }
+}
+
+impl PackageIndex
+{
+  pub fn from_directory(path: &Path) -> anyhow::Result<Self>
+  {
+    let index = std::fs::read_to_string(path.join(PACKAGE_INDEX_FILE))
+      .context(format!("package index not found in {}", path.display()))?;
+    Ok(toml::from_str(&index)?)
+  }
+
+  pub fn save(&self, path: &Path) -> anyhow::Result<()>
+  {
+    std::fs::create_dir_all(path)?;
+    std::fs::write(path.join(PACKAGE_INDEX_FILE), toml::to_string(&self)?)?;
+    Ok(())
+  }
+
+  /// Adds a file located at `root`/`file` to index. `file` is stored relative to `root`
+  pub fn add(&mut self, root: &Path, file: &Path) -> anyhow::Result<()>
+  {
+    let entry = PackageIndexEntry {
+      file: file
+        .to_str()
+        .context("failed to convert path to string")?
+        .replace('\\', "/"),
+      md5: md5_file(&root.join(file))?
+    };
+    self.packages.retain(|x| x.file != entry.file);
+    self.packages.push(entry);
+    Ok(())
+  }
+
+  pub fn verify(root: &Path, entry: &PackageIndexEntry) -> anyhow::Result<()>
+  {
+    let actual = md5_file(&root.join(&entry.file))?;
+    ensure!(actual == entry.md5, "checksum mismatch for {} (expected {}, got {})", entry.file, entry.md5, actual);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_add_and_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = Path::new("fmt-1.1.3-x86_64-linux-static.tar.gz");
+    std::fs::write(dir.path().join(file), b"contents").unwrap();
+
+    let mut index = PackageIndex::default();
+    index.add(dir.path(), file).unwrap();
+    index.add(dir.path(), file).unwrap();
+    assert_eq!(index.packages.len(), 1);
+    assert_eq!(index.packages[0].dependency().unwrap().name, "fmt");
+
+    index.save(dir.path()).unwrap();
+    let loaded = PackageIndex::from_directory(dir.path()).unwrap();
+    assert_eq!(loaded.packages, index.packages);
+    assert!(PackageIndex::verify(dir.path(), &loaded.packages[0]).is_ok());
+
+    std::fs::write(dir.path().join(file), b"tampered").unwrap();
+    assert!(PackageIndex::verify(dir.path(), &loaded.packages[0]).is_err());
+  }
+}
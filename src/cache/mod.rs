@@ -1,3 +1,5 @@
 mod cache;
+mod index;
 
-pub use cache::Cache;
\ No newline at end of file
+pub use cache::Cache;
+pub use index::PackageIndex;
@@ -32,8 +32,30 @@ pub enum Command
   /// Pack and push package to Artifactory
   Publish(PublishArgs),
 
+  /// Copy all resolved packages into a project-local directory for offline use
+  Vendor(VendorArgs),
+
   /// Purge selected local folders
   Purge(PurgeArgs),
+
+  /// Export or import cached packages
+  #[clap(subcommand)] Cache(CacheCommand),
+
+  /// Print environment variables for using installed dependencies
+  Env(EnvArgs),
+
+  /// Start a subshell with installed dependencies activated
+  Shell(ShellArgs),
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum CacheCommand
+{
+  /// Write all cached packages required by manifest into a single bundle
+  Export(CacheExportArgs),
+
+  /// Verify and merge a bundle into local cache
+  Import(CacheImportArgs),
 }
 
 #[derive(clap::Subcommand, Debug, Clone)]
@@ -58,7 +80,8 @@ pub struct RegistryAddArgs
   /// URL of the Artifactory registry, stripped of any trailing slashes and without repository name
   #[arg(long)] pub url: String,
 
-  /// Package layout pattern in selected repository in Artifactory. May lead to errors and bugs, use with caution
+  /// Package layout pattern in selected repository in Artifactory. Must contain `{ext}` placeholder or end with
+  /// archive extension (`.tar.gz`, `.tar.zst` or `.tar.xz`), which is replaced according to package compression
   #[arg(short, long)] pub pattern: Option<String>,
 
   /// Username for basic auth in Artifactory
@@ -66,6 +89,12 @@ pub struct RegistryAddArgs
 
   /// Token for basic auth in Artifactory
   #[arg(short, long)] pub token: Option<String>,
+
+  /// Use this registry by default for uploading packages built from sources
+  #[arg(long)] pub upload_built: bool,
+
+  /// Compression used for packages published to this registry (gzip, zstd or xz)
+  #[arg(short, long)] pub compression: Option<crate::types::Compression>,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -85,10 +114,11 @@ pub enum ToolchainCommand
 #[derive(clap::Args, Debug, Clone)]
 pub struct ToolchainCmakeArgs
 {
-  /// Override CMake configure-step binary to use. Useful for cross-compiling (e.g., `x86_64-w64-mingw32-cmake` for Windows).
+  /// Override CMake configure-step binary to use. Deprecated: applies to every build without `--target`,
+  /// define a cross-compilation target in config instead.
   #[arg(long)] pub configure_command: Option<String>,
 
-  /// Additional CMake configure-step arguments. Useful for cross-compiling (e.g., `--target=x86_64-w64-mingw32` for Windows).
+  /// Additional CMake configure-step definitions as `NAME=VALUE` or typed `NAME:TYPE=VALUE` (e.g., `CMAKE_SYSROOT:PATH=/opt/sysroot`).
   #[arg(long)]
   #[clap(num_args = 0.., value_delimiter = ',')]
   pub configure_args: Option<Vec<String>>,
@@ -104,6 +134,26 @@ pub struct BuildArgs
   #[arg(short, long)] pub dist: crate::types::Distribution,
 }
 
+#[derive(clap::Args, Debug, Clone)]
+pub struct EnvArgs
+{
+  /// Folder where manifest is located
+  pub folder: Option<String>,
+
+  /// Shell syntax of printed exports (bash, zsh, fish, pwsh). Detected from $SHELL by default
+  #[arg(long)] pub shell: Option<crate::types::Shell>,
+
+  /// Print variables as a json object instead of shell exports
+  #[arg(long)] pub json: bool
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ShellArgs
+{
+  /// Folder where manifest is located
+  pub folder: Option<String>
+}
+
 #[derive(clap::Args, Debug, Clone)]
 pub struct InstallArgs
 {
@@ -120,7 +170,25 @@ pub struct InstallArgs
   #[arg(short, long)] pub fresh: bool,
 
   /// Compile all packages from source
-  #[arg(short, long)] pub source_only: bool
+  #[arg(short, long)] pub source_only: bool,
+
+  /// Upload packages built from sources to the given registry
+  #[arg(long)] pub upload_built: Option<String>,
+
+  /// Also install all packages into a single merged prefix
+  #[arg(long)] pub merged: bool,
+
+  /// Stream build output of packages built from sources
+  #[arg(short, long)] pub verbose: bool,
+
+  /// Build profile (debug, release, relwithdebinfo, minsizerel or one defined in config)
+  #[arg(short, long)] pub profile: Option<String>,
+
+  /// Cross-compilation target defined in config. Sets target os and architecture
+  #[arg(short, long)] pub target: Option<String>,
+
+  /// Number of packages built from sources concurrently. Available cores are split between them
+  #[arg(short, long)] pub jobs: Option<usize>
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -129,8 +197,26 @@ pub struct PackArgs
   /// Folder where manifest is located
   pub folder: Option<String>,
 
-  /// Output path
-  #[arg(short, long)] pub output: Option<String>
+  /// Output file or directory
+  #[arg(short, long)] pub output: Option<String>,
+
+  /// Print files that would be packed and exit
+  #[arg(short, long)] pub list: bool,
+
+  /// Type of distribution. Binary distributions are packed from export folder
+  #[arg(short, long)] pub dist: Option<crate::types::Distribution>,
+
+  /// Package architecture (defaults to host architecture)
+  #[arg(short, long)] pub arch: Option<crate::types::Arch>,
+
+  /// Package operating system (defaults to host operating system)
+  #[arg(long)] pub os: Option<crate::types::OperatingSystem>,
+
+  /// Compression format (gzip, zstd or xz)
+  #[arg(short, long, default_value = "gzip")] pub compression: crate::types::Compression,
+
+  /// Build profile of binary distribution (defaults to release)
+  #[arg(short, long)] pub profile: Option<String>
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -152,18 +238,76 @@ pub struct PublishArgs
   #[arg(short, long)] pub os: Option<crate::types::OperatingSystem>,
 
   /// Overwrite existing package
-  #[arg(short, long)] pub force: bool
+  #[arg(short, long)] pub force: bool,
+
+  /// Compression format (gzip, zstd or xz). Defaults to registry setting
+  #[arg(short, long)] pub compression: Option<crate::types::Compression>,
+
+  /// Build profile of binary distribution (defaults to release)
+  #[arg(short, long)] pub profile: Option<String>
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct VendorArgs
+{
+  /// Directory to copy packages into, relative to manifest folder (defaults to `vendor`)
+  pub directory: Option<String>,
+
+  /// Folder where manifest is located
+  #[arg(short, long)] pub path: Option<String>,
+
+  /// Target operating systems
+  #[arg(short, long)]
+  #[clap(num_args = 0.., value_delimiter = ',')]
+  pub os: Option<Vec<crate::types::OperatingSystem>>,
+
+  /// Target architectures
+  #[arg(short, long)]
+  #[clap(num_args = 0.., value_delimiter = ',')]
+  pub arch: Option<Vec<crate::types::Arch>>,
+
+  /// Also vendor source tarballs of every dependency
+  #[arg(short, long)] pub sources: bool,
+
+  /// Do not vendor binary tarballs (implies --sources)
+  #[arg(long)] pub no_binaries: bool
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheExportArgs
+{
+  /// Manifest file or folder where manifest is located. Lockfiles are not supported
+  #[arg(long = "for")] pub manifest: Option<String>,
+
+  /// Output bundle path
+  #[arg(short, long)] pub output: String,
+
+  /// Target OS
+  #[arg(long)] pub os: Option<crate::types::OperatingSystem>,
+
+  /// Target architecture
+  #[arg(short, long)] pub arch: Option<crate::types::Arch>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheImportArgs
+{
+  /// Path to bundle created by `puff cache export`
+  pub bundle: String,
 }
 
 #[derive(clap::Args, Debug, Clone)]
 pub struct PurgeArgs
 {
-  /// Purge all folders (implies --cache --config)
+  /// Purge all folders (implies --cache --config --store)
   #[arg(long)] pub all: bool,
 
   /// Purge cache folder (implies --all)
   #[arg(long)] pub cache: bool,
 
   /// Purge config folder (implies --all)
-  #[arg(long)] pub config: bool
+  #[arg(long)] pub config: bool,
+
+  /// Purge package store. Files installed as hardlinks stay in projects
+  #[arg(long)] pub store: bool
 }
\ No newline at end of file
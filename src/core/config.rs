@@ -4,6 +4,7 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use crate::core;
 use crate::core::args::{Command, RegistryCommand, ToolchainCommand};
+use crate::types::{CMakeDefinition, Compression};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Config
@@ -12,12 +13,87 @@ pub struct Config
   pub directories: Rc<core::Directories>,
   pub registry: RegistryConfig,
   pub toolchain: ToolchainConfig,
+  #[serde(default)]
+  pub install: InstallConfig,
+
+  /// User-defined build profiles, selected with `puff install --profile <name>`
+  #[serde(default)]
+  pub profiles: HashMap<String, ProfileConfig>,
+
+  /// Cross-compilation targets, selected with `puff install --target <name>`. Settings of selected target replace
+  /// global `[toolchain.cmake]` ones
+  #[serde(default)]
+  pub targets: HashMap<String, TargetConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TargetConfig
+{
+  /// Name of the target in config
+  #[serde(skip)]
+  pub name: String,
+
+  /// Target architecture and operating system, e.g. `aarch64` and `linux`
+  pub arch: String,
+  pub os: String,
+
+  /// GNU triple passed to `--host` of configure scripts. Derived from arch and os if omitted
+  pub host: Option<String>,
+
+  /// CMake binary to use for this target, e.g. `x86_64-w64-mingw32-cmake`
+  pub cmake_command: Option<String>,
+  pub toolchain_file: Option<String>,
+  pub sysroot: Option<String>,
+
+  /// Compiler launcher such as `ccache`
+  pub compiler_launcher: Option<String>,
+
+  #[serde(default)]
+  pub env: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ProfileConfig
+{
+  /// CMake build type the profile is based on. Defaults to `Release`
+  pub build_type: Option<String>,
+  pub cflags: Option<String>,
+  pub cxxflags: Option<String>,
+  pub ldflags: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct InstallConfig
+{
+  pub link_mode: LinkMode,
+
+  /// Additionally materialize all packages into a single `dependencies/.puff/merged` tree
+  pub merged: bool,
+
+  /// Build profile used when `--profile` is not given
+  pub profile: Option<String>,
+
+  /// Number of packages built from sources concurrently when `--jobs` is not given
+  pub jobs: Option<usize>,
+}
+
+/// How files from package store are materialized in `dependencies` folder
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode
+{
+  #[default] Hardlink,
+  Symlink,
+  Copy
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct RegistryConfig
 {
   pub list: Vec<RegistryData>,
+  pub upload_built: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,8 +101,12 @@ pub struct RegistryData
 {
   pub name: String,
   pub base_url: String,
+
+  /// Package path in registry. Archive extension is given by `{ext}` placeholder or by trailing `.tar.gz`,
+  /// `.tar.zst` or `.tar.xz`, which is replaced according to package compression
   pub pattern: String,
   pub auth: Option<RegistryAuth>,
+  pub compression: Option<Compression>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -43,11 +123,13 @@ pub struct ToolchainConfig
   pub cmake: CMakeConfig,
 }
 
+/// Global cmake settings, set with `puff toolchain cmake`. Deprecated in favor of `[targets]`: they are applied only to
+/// builds without `--target`, which includes native ones, and a warning is printed when they are not default
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CMakeConfig
 {
   pub configure_command: String,
-  pub configure_additional_definitions: HashMap<String, String>,
+  pub configure_additional_definitions: HashMap<String, CMakeDefinition>,
 }
 
 impl Default for CMakeConfig
@@ -62,6 +144,14 @@ impl Default for CMakeConfig
   }
 }
 
+impl CMakeConfig
+{
+  pub fn is_default(&self) -> bool
+  {
+    self.configure_command == "cmake" && self.configure_additional_definitions.is_empty()
+  }
+}
+
 impl Default for RegistryData
 {
   fn default() -> Self
@@ -71,7 +161,8 @@ impl Default for RegistryData
       name: String::new(),
       base_url: String::new(), // http://uav.radar-mms.com/artifactory/{name}
       pattern: String::from("parcels/{org}/{name}/{version}/{name}-{version}-{arch}-{platform}-{dist}.tar.gz"),
-      auth: None
+      auth: None,
+      compression: None
     }
   }
 }
@@ -105,7 +196,8 @@ impl Config
                   name: a.name.clone(),
                   base_url: a.url.clone(),
                   pattern: a.pattern.clone().unwrap_or("parcels/radar/{name}/{version}/{name}-{version}-{arch}-{platform}-{dist}.tar.gz".to_string()),
-                  auth: None
+                  auth: None,
+                  compression: a.compression
                 };
 
                 if let Some(u) = &a.username {
@@ -116,6 +208,9 @@ impl Config
                 }
 
                 self.registry.list.push(reg_data);
+                if a.upload_built {
+                  self.registry.upload_built = Some(a.name.clone());
+                }
                 self.save()?;
                 println!("added registry {} to config", a.name.yellow().bold());
                 Ok(())
@@ -123,6 +218,9 @@ impl Config
               RegistryCommand::Remove(a) => {
                 return if let Some(i) = self.registry.list.iter().position(|x| x.name == a.name) {
                   self.registry.list.remove(i);
+                  if self.registry.upload_built.as_ref() == Some(&a.name) {
+                    self.registry.upload_built = None;
+                  }
                   self.save()?;
                   println!("removed registry {} from config", a.name.yellow().bold());
                   Ok(())
@@ -148,14 +246,8 @@ impl Config
                 if let Some(args) = &a.configure_args {
                   let configure_additional_definitions = args
                     .iter()
-                    .map(|x| {
-                      let x = x.trim();
-                      let mut parts = x.splitn(2, '=');
-                      let key = parts.next().unwrap();
-                      let value = parts.next().unwrap_or("");
-                      (key.to_string(), value.to_string())
-                    })
-                    .collect::<HashMap<String, String>>();
+                    .map(|x| CMakeDefinition::from_argument(x))
+                    .collect::<anyhow::Result<HashMap<String, CMakeDefinition>>>()?;
                   self.toolchain.cmake.configure_additional_definitions = configure_additional_definitions;
                 };
                 if let Some(cmake) = &a.configure_command {
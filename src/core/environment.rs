@@ -1,22 +1,44 @@
 use colored::Colorize;
 use crate::{types, core};
+use std::str::FromStr;
+use anyhow::{ensure, Context};
 use crate::core::args::Command;
 
 pub struct Environment
 {
   pub os: types::OperatingSystem,
-  pub arch: types::Arch
+  pub arch: types::Arch,
+
+  /// Cross-compilation target profile from config, if selected
+  pub target: Option<core::TargetConfig>
 }
 
 impl Environment
 {
-  pub fn new(args: &core::Args) -> anyhow::Result<Self>
+  pub fn new(args: &core::Args, config: &core::Config) -> anyhow::Result<Self>
   {
     let mut os = types::OperatingSystem::from_env();
     let mut arch = types::Arch::from_env()?;
+    let mut target = None;
     match &args.command {
       Some(x) => match x {
         Command::Install(y) => {
+          if let Some(name) = &y.target {
+            ensure!(y.os.is_none() && y.arch.is_none(), "--target can not be combined with --os or --arch");
+            let mut profile = config.targets
+              .get(name)
+              .context(format!("target {} is not defined in config", name))?
+              .clone();
+            profile.name = name.clone();
+            os = types::OperatingSystem::from_str(profile.os.as_str())?;
+            arch = types::Arch::from_str(profile.arch.as_str())?;
+            target = Some(profile);
+          } else if !config.toolchain.cmake.is_default() {
+            eprintln!("{}: global cmake toolchain settings are deprecated and are applied to native builds too, \
+              define a target in config and select it with --target instead",
+              "warning".yellow().bold()
+            );
+          }
           if y.os.is_some() { os = y.os.unwrap(); }
           if y.arch.is_some() { arch = y.arch.unwrap(); }
         },
@@ -26,16 +48,54 @@ impl Environment
     }
     Ok(Self {
       os,
-      arch
+      arch,
+      target
     })
   }
 
+  /// Whether target differs from the machine puff is running on. Selected target profile is always treated as cross
+  pub fn is_cross(&self) -> bool
+  {
+    self.target.is_some()
+      || self.os != types::OperatingSystem::from_env()
+      || types::Arch::from_env().map_or(true, |x| x != self.arch)
+  }
+
+  /// GNU target triple, as passed to `--host` of configure scripts
+  pub fn host_triple(&self) -> String
+  {
+    if let Some(host) = self.target.as_ref().and_then(|x| x.host.clone()) {
+      return host;
+    }
+    let arch = match self.arch {
+      types::Arch::Aarch64 | types::Arch::ArmV8 => "aarch64",
+      types::Arch::Arm => "arm",
+      types::Arch::ArmV5TE => "armv5te",
+      types::Arch::ArmV7 | types::Arch::ArmV7A | types::Arch::ArmV7R => "armv7a",
+      types::Arch::Loongarch64 => "loongarch64",
+      types::Arch::X86_64 | types::Arch::Unknown => "x86_64"
+    };
+    let arm32 = arch.starts_with("arm");
+    match self.os {
+      types::OperatingSystem::Windows => format!("{}-w64-mingw32", arch),
+      types::OperatingSystem::MacOS => format!("{}-apple-darwin", arch),
+      types::OperatingSystem::Android if arm32 => format!("{}-linux-androideabi", arch),
+      types::OperatingSystem::Android => format!("{}-linux-android", arch),
+      _ if arm32 => format!("{}-linux-gnueabihf", arch),
+      _ => format!("{}-linux-gnu", arch)
+    }
+  }
+
   pub fn pretty_print(&self) -> String
   {
-    format!("target os:   {}\n\
-             target arch: {}",
-            self.os.to_string().green().bold(),
-            self.arch.to_string().yellow().bold()
-    )
+    let mut text = format!("target os:   {}\n\
+                            target arch: {}",
+                           self.os.to_string().green().bold(),
+                           self.arch.to_string().yellow().bold()
+    );
+    if let Some(target) = &self.target {
+      text.push_str(format!("\ntarget:      {}", target.name.cyan().bold()).as_str());
+    }
+    text
   }
 }
\ No newline at end of file
@@ -4,6 +4,6 @@ mod directories;
 mod environment;
 
 pub use directories::Directories;
-pub use config::Config;
+pub use config::{Config, LinkMode, ProfileConfig, TargetConfig};
 pub use args::Args;
 pub use environment::Environment;
\ No newline at end of file
@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use crate::core;
+use crate::names::{CMAKE_INTEGRATION_FILE, CMAKE_PRESET_NAME, CMAKE_USER_PRESETS_FILE, DEPENDENCIES_FOLDER, GENERATED_FOLDER};
+use crate::toolchains::utl::{dependency_prefixes, generated_directory};
+
+/// Generates `dependencies/.puff/puff.cmake` for installed packages and registers it in `CMakeUserPresets.json`
+/// if project is a CMake project
+pub fn generate(project_directory: &str, env: &core::Environment) -> anyhow::Result<()>
+{
+  let prefixes = dependency_prefixes(project_directory)?;
+  let cross = env.is_cross();
+  let directory = generated_directory(project_directory);
+  std::fs::create_dir_all(&directory)?;
+  let file = directory.join(CMAKE_INTEGRATION_FILE);
+  std::fs::write(&file, integration_file(&prefixes, cross))?;
+
+  if Path::new(project_directory).join("CMakeLists.txt").exists() {
+    update_user_presets(Path::new(project_directory))?;
+  }
+  Ok(())
+}
+
+fn integration_file(prefixes: &[PathBuf], cross: bool) -> String
+{
+  let quoted = |paths: &[PathBuf]| paths
+    .iter()
+    .map(|x| format!("  \"{}\"\n", cmake_path(x)))
+    .collect::<String>();
+  let library_directories = prefixes
+    .iter()
+    .map(|x| x.join("lib"))
+    .filter(|x| x.is_dir())
+    .collect::<Vec<_>>();
+
+  let mut content = String::from("# generated by puff, do not edit\n\n");
+  content.push_str(format!("set(PUFF_PREFIXES\n{})\n\n", quoted(prefixes)).as_str());
+  content.push_str("list(PREPEND CMAKE_PREFIX_PATH ${PUFF_PREFIXES})\n");
+  content.push_str("list(REMOVE_DUPLICATES CMAKE_PREFIX_PATH)\n");
+  if cross {
+    content.push_str("list(PREPEND CMAKE_FIND_ROOT_PATH ${PUFF_PREFIXES})\n");
+    content.push_str("list(REMOVE_DUPLICATES CMAKE_FIND_ROOT_PATH)\n");
+  }
+  if !library_directories.is_empty() {
+    content.push_str(format!("\nlist(APPEND CMAKE_BUILD_RPATH\n{})\n", quoted(&library_directories)).as_str());
+    content.push_str("list(REMOVE_DUPLICATES CMAKE_BUILD_RPATH)\n");
+    content.push_str("set(CMAKE_INSTALL_RPATH_USE_LINK_PATH ON)\n");
+  }
+  content
+}
+
+/// Adds or replaces `puff` configure preset, keeping all other user presets intact
+fn update_user_presets(project_directory: &Path) -> anyhow::Result<()>
+{
+  let file = project_directory.join(CMAKE_USER_PRESETS_FILE);
+  let mut presets: serde_json::Value = match file.exists() {
+    true => serde_json::from_str(&std::fs::read_to_string(&file)?)
+      .context(format!("failed to parse {}", file.display()))?,
+    false => serde_json::json!({ "version": 3 })
+  };
+  let root = presets
+    .as_object_mut()
+    .context(format!("{} is not a json object", file.display()))?;
+  let configure_presets = root
+    .entry("configurePresets")
+    .or_insert(serde_json::json!([]))
+    .as_array_mut()
+    .context(format!("configurePresets in {} is not an array", file.display()))?;
+  configure_presets.retain(|x| x.get("name").and_then(|y| y.as_str()) != Some(CMAKE_PRESET_NAME));
+  configure_presets.push(serde_json::json!({
+    "name": CMAKE_PRESET_NAME,
+    "displayName": "puff dependencies",
+    "binaryDir": "${sourceDir}/build/puff",
+    "cacheVariables": {
+      "CMAKE_PROJECT_INCLUDE_BEFORE": format!("${{sourceDir}}/{}/{}/{}", DEPENDENCIES_FOLDER, GENERATED_FOLDER, CMAKE_INTEGRATION_FILE)
+    }
+  }));
+  std::fs::write(&file, serde_json::to_string_pretty(&presets)?)?;
+  Ok(())
+}
+
+fn cmake_path(path: &Path) -> String { path.to_string_lossy().replace('\\', "/") }
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_update_user_presets() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+      dir.path().join(CMAKE_USER_PRESETS_FILE),
+      r#"{ "version": 4, "configurePresets": [{ "name": "mine" }, { "name": "puff", "binaryDir": "old" }] }"#
+    ).unwrap();
+    update_user_presets(dir.path()).unwrap();
+    update_user_presets(dir.path()).unwrap();
+
+    let presets: serde_json::Value = serde_json::from_str(
+      &std::fs::read_to_string(dir.path().join(CMAKE_USER_PRESETS_FILE)).unwrap()
+    ).unwrap();
+    let names = presets["configurePresets"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|x| x["name"].as_str().unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(presets["version"], 4);
+    assert_eq!(names, vec!["mine", "puff"]);
+    assert_eq!(presets["configurePresets"][1]["binaryDir"], "${sourceDir}/build/puff");
+  }
+}
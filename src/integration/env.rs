@@ -0,0 +1,143 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::integration::pkgconfig::pkgconfig_directory;
+use crate::toolchains::utl::dependency_prefixes;
+use crate::types::{OperatingSystem, Shell};
+
+/// Path list variables for using installed dependencies of a project. Every variable is prepended
+/// to its current value in the environment
+pub struct ActivationEnvironment
+{
+  pub variables: Vec<(String, Vec<PathBuf>)>
+}
+
+impl ActivationEnvironment
+{
+  pub fn new(project_directory: &str) -> anyhow::Result<Self>
+  {
+    let prefixes = dependency_prefixes(project_directory)?;
+    let existing = |suffix: &str| prefixes
+      .iter()
+      .map(|x| x.join(suffix))
+      .filter(|x| x.is_dir())
+      .collect::<Vec<_>>();
+    let library_variable = match OperatingSystem::from_env() {
+      OperatingSystem::Windows => "PATH",
+      OperatingSystem::MacOS => "DYLD_LIBRARY_PATH",
+      _ => "LD_LIBRARY_PATH"
+    };
+
+    let mut environment = Self { variables: Vec::new() };
+    environment.push("PATH", existing("bin"));
+    environment.push(library_variable, existing("lib"));
+    environment.push("PKG_CONFIG_PATH", std::iter::once(pkgconfig_directory(project_directory))
+      .filter(|x| x.is_dir())
+      .map(|x| x.canonicalize())
+      .collect::<Result<Vec<_>, _>>()?
+    );
+    environment.push("CMAKE_PREFIX_PATH", prefixes);
+    Ok(environment)
+  }
+
+  fn push(&mut self, name: &str, paths: Vec<PathBuf>)
+  {
+    if paths.is_empty() {
+      return;
+    }
+    match self.variables.iter_mut().find(|x| x.0 == name) {
+      Some(x) => x.1.extend(paths),
+      None => self.variables.push((name.to_string(), paths))
+    }
+  }
+
+  /// Full values of variables, including their current values in the environment
+  pub fn values(&self) -> anyhow::Result<Vec<(String, OsString)>>
+  {
+    self.variables
+      .iter()
+      .map(|(name, paths)| {
+        let current = std::env::var_os(name)
+          .map(|x| std::env::split_paths(&x).collect::<Vec<_>>())
+          .unwrap_or_default();
+        Ok((name.clone(), std::env::join_paths(paths.iter().chain(current.iter()))?))
+      })
+      .collect()
+  }
+
+  pub fn to_json(&self) -> anyhow::Result<String>
+  {
+    let values = self.values()?
+      .into_iter()
+      .map(|(name, value)| (name, serde_json::Value::from(value.to_string_lossy().to_string())))
+      .collect::<serde_json::Map<_, _>>();
+    Ok(serde_json::to_string_pretty(&values)?)
+  }
+
+  /// Script that prepends paths to variables when evaluated by `shell`
+  pub fn to_script(&self, shell: Shell) -> String
+  {
+    self.variables
+      .iter()
+      .map(|(name, paths)| {
+        let quoted = paths
+          .iter()
+          .map(|x| quote(x, shell))
+          .collect::<Vec<_>>();
+        match shell {
+          Shell::Bash | Shell::Zsh => format!(
+            "export {name}=\"{}${{{name}:+{}${name}}}\"\n",
+            quoted.join(separator()),
+            separator()
+          ),
+          Shell::Fish => format!("set -gx {name} \"{}\" ${name}\n", quoted.join("\" \"")),
+          Shell::Pwsh => format!(
+            "$env:{name} = @('{}', $env:{name}) -join [IO.Path]::PathSeparator\n",
+            quoted.join("', '")
+          )
+        }
+      })
+      .collect()
+  }
+}
+
+fn separator() -> &'static str { if cfg!(windows) { ";" } else { ":" } }
+
+/// Escapes path for double quotes in posix shells and fish, or single quotes in powershell
+fn quote(path: &Path, shell: Shell) -> String
+{
+  let path = path.to_string_lossy();
+  match shell {
+    Shell::Pwsh => path.replace('\'', "''"),
+    Shell::Fish => path
+      .replace('\\', "\\\\")
+      .replace('"', "\\\"")
+      .replace('$', "\\$"),
+    Shell::Bash | Shell::Zsh => path
+      .replace('\\', "\\\\")
+      .replace('"', "\\\"")
+      .replace('$', "\\$")
+      .replace('`', "\\`")
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_to_script() {
+    let environment = ActivationEnvironment {
+      variables: vec![(String::from("PKG_CONFIG_PATH"), vec![PathBuf::from("/p/dependencies/pkgconfig")])]
+    };
+    assert_eq!(
+      environment.to_script(Shell::Fish),
+      "set -gx PKG_CONFIG_PATH \"/p/dependencies/pkgconfig\" $PKG_CONFIG_PATH\n"
+    );
+    #[cfg(unix)]
+    assert_eq!(
+      environment.to_script(Shell::Bash),
+      "export PKG_CONFIG_PATH=\"/p/dependencies/pkgconfig${PKG_CONFIG_PATH:+:$PKG_CONFIG_PATH}\"\n"
+    );
+  }
+}
@@ -0,0 +1,3 @@
+pub mod cmake;
+pub mod env;
+pub mod pkgconfig;
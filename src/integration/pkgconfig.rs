@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use crate::manifest::Manifest;
+use crate::names::{MANIFEST_FILE, PKGCONFIG_FOLDER};
+use crate::toolchains::utl::{dependency_prefixes, generated_directory};
+
+/// Directories inside a prefix where packages usually ship their `.pc` files
+const PKGCONFIG_DIRECTORIES: [&str; 3] = ["lib/pkgconfig", "lib64/pkgconfig", "share/pkgconfig"];
+
+/// Collects `.pc` files of all installed packages into `dependencies/.puff/pkgconfig`, relocated to their
+/// install prefixes. Packages without `.pc` files get one synthesized from manifest and `lib` folder.
+/// Files are always written anew, so files hardlinked from package store are never modified
+pub fn generate(project_directory: &str) -> anyhow::Result<()>
+{
+  let target = pkgconfig_directory(project_directory);
+  if target.exists() {
+    std::fs::remove_dir_all(&target)?;
+  }
+  std::fs::create_dir_all(&target)?;
+
+  for prefix in dependency_prefixes(project_directory)? {
+    let files = PKGCONFIG_DIRECTORIES
+      .iter()
+      .map(|x| prefix.join(x))
+      .filter(|x| x.is_dir())
+      .flat_map(|x| std::fs::read_dir(x).into_iter().flatten().flatten())
+      .map(|x| x.path())
+      .filter(|x| x.extension().is_some_and(|y| y == "pc"))
+      .collect::<Vec<_>>();
+    if files.is_empty() {
+      if let Some((name, content)) = synthesize(&prefix)? {
+        std::fs::write(target.join(format!("{}.pc", name)), content)?;
+      }
+      continue;
+    }
+    for x in files {
+      let content = relocate(&std::fs::read_to_string(&x)?, &prefix);
+      std::fs::write(target.join(x.file_name().unwrap()), content)?;
+    }
+  }
+  Ok(())
+}
+
+pub fn pkgconfig_directory(project_directory: &str) -> PathBuf
+{
+  generated_directory(project_directory).join(PKGCONFIG_FOLDER)
+}
+
+/// Points `prefix=` to `prefix` and replaces every other occurrence of the original prefix
+fn relocate(content: &str, prefix: &Path) -> String
+{
+  let new_prefix = pc_path(prefix);
+  let old_prefix = content
+    .lines()
+    .find_map(|x| x.trim().strip_prefix("prefix="))
+    .map(|x| x.trim().to_string())
+    .filter(|x| !x.is_empty());
+  content
+    .lines()
+    .map(|x| match x.trim().starts_with("prefix=") {
+      true => format!("prefix={}", new_prefix),
+      false => match &old_prefix {
+        Some(old) if !old.contains("${") && old != "/" => x.replace(old.as_str(), "${prefix}"),
+        _ => x.to_string()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n") + "\n"
+}
+
+/// Returns package name and `.pc` file contents
+fn synthesize(prefix: &Path) -> anyhow::Result<Option<(String, String)>>
+{
+  let libraries = libraries(&prefix.join("lib"))?;
+  if libraries.is_empty() && !prefix.join("include").is_dir() {
+    return Ok(None);
+  }
+  let (name, version, description) = match prefix.join(MANIFEST_FILE).exists() {
+    true => {
+      let manifest = Manifest::from_directory(prefix.to_str().unwrap())?;
+      (manifest.this.name, manifest.this.version.min.to_string(), manifest.this.description)
+    },
+    false => (package_name(prefix), String::from("0.0.0"), None)
+  };
+  Ok(Some((name.clone(), format!(
+    "prefix={}\nexec_prefix=${{prefix}}\nlibdir=${{prefix}}/lib\nincludedir=${{prefix}}/include\n\n\
+     Name: {}\nDescription: {}\nVersion: {}\nLibs: -L${{libdir}}{}\nCflags: -I${{includedir}}\n",
+    pc_path(prefix),
+    name,
+    description.unwrap_or(format!("{} (generated by puff)", name)),
+    version,
+    libraries
+      .iter()
+      .map(|x| format!(" -l{}", x))
+      .collect::<String>()
+  ))))
+}
+
+/// Library names as passed to `-l`, found in `directory`
+fn libraries(directory: &Path) -> anyhow::Result<BTreeSet<String>>
+{
+  let mut libraries = BTreeSet::new();
+  if !directory.is_dir() {
+    return Ok(libraries);
+  }
+  for x in std::fs::read_dir(directory)? {
+    let file_name = x?.file_name().to_string_lossy().to_string();
+    let stem = [".a", ".so", ".dylib", ".lib"]
+      .iter()
+      .find_map(|ext| file_name
+        .strip_suffix(ext)
+        .or_else(|| file_name.split_once(".so.").map(|y| y.0).filter(|_| *ext == ".so"))
+      );
+    if let Some(stem) = stem {
+      libraries.insert(stem.strip_prefix("lib").unwrap_or(stem).to_string());
+    }
+  }
+  Ok(libraries)
+}
+
+fn package_name(prefix: &Path) -> String
+{
+  prefix
+    .file_name()
+    .map(|x| x.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+fn pc_path(path: &Path) -> String { path.to_string_lossy().replace('\\', "/") }
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_relocate() {
+    let content = "prefix=/opt/build/export\nlibdir=/opt/build/export/lib\nincludedir=${prefix}/include\n\nName: fmt\nLibs: -L${libdir} -lfmt\n";
+    let relocated = relocate(content, Path::new("/home/user/project/dependencies/fmt"));
+    assert_eq!(
+      relocated,
+      "prefix=/home/user/project/dependencies/fmt\nlibdir=${prefix}/lib\nincludedir=${prefix}/include\n\nName: fmt\nLibs: -L${libdir} -lfmt\n"
+    );
+  }
+
+  #[test]
+  fn test_libraries() {
+    let dir = tempfile::tempdir().unwrap();
+    for x in ["libfmt.a", "libz.so.1.3", "libz.so", "libssl.dylib", "zlib.lib", "cmake"] {
+      std::fs::write(dir.path().join(x), b"").unwrap();
+    }
+    assert_eq!(
+      libraries(dir.path()).unwrap().into_iter().collect::<Vec<_>>(),
+      vec!["fmt", "ssl", "z", "zlib"]
+    );
+  }
+}
@@ -1,6 +1,5 @@
 use std::path::Path;
 use std::rc::Rc;
-use anyhow::Context;
 use clap::Parser;
 use colored::Colorize;
 use crate::core::args::Command;
@@ -20,8 +19,12 @@ mod pack;
 mod artifactory;
 mod cache;
 mod resolver;
+mod integration;
+mod store;
+mod vendor;
 
-fn try_main() -> anyhow::Result<()> {
+/// Returns exit code of the process
+fn try_main() -> anyhow::Result<i32> {
   let args = Rc::new(core::Args::parse());
 
   if args.version {
@@ -44,13 +47,13 @@ fn try_main() -> anyhow::Result<()> {
     println!("written in rust with love");
     println!("copyright {}", "whs31 © 2024".blue().bold());
 
-    return Ok(());
+    return Ok(0);
   }
 
   let mut config = core::Config::create_or_load()?;
   config.process_args(&args)?;
   let config = Rc::new(config);
-  let env = Rc::new(core::Environment::new(&args)?);
+  let env = Rc::new(core::Environment::new(&args, &config)?);
 
   let mut puff = puff::Puff::new(config, args.clone(), env)?;
 
@@ -58,15 +61,19 @@ fn try_main() -> anyhow::Result<()> {
   match &args.command {
     Some(command) => match command {
       Command::Install(x) => {
-        puff
-          .sync()?
-          .install(x)?;
+        if let Err(e) = puff.sync() {
+          let folder = x.folder
+            .clone()
+            .unwrap_or(std::env::current_dir()?.into_os_string().into_string().unwrap());
+          if puff.vendor_for(folder.as_str())?.is_none() {
+            return Err(e);
+          }
+          eprintln!("{}: {} (using vendored packages)", "warning".yellow().bold(), e.to_string().yellow().bold());
+        }
+        puff.install(x)?;
       },
-      Command::Pack(x) => { puff.pack(
-        x.folder
-          .as_ref()
-          .unwrap_or(&std::env::current_dir()?.into_os_string().into_string().unwrap())
-          .as_str())?;
+      Command::Pack(x) => {
+        puff.pack_with_args(x)?;
       },
       Command::Registry(_x) => {
         let _ = puff
@@ -89,7 +96,8 @@ fn try_main() -> anyhow::Result<()> {
                         .unwrap()
                     ).as_str(),
                   x.name.as_str(),
-                  x.force
+                  x.force,
+                  x.compression
                 )?;
             },
             _ => {
@@ -109,14 +117,7 @@ fn try_main() -> anyhow::Result<()> {
               } else {
                 puff
                   .sync()?
-                  .publish_target(
-                    export_folder.to_str().unwrap(),
-                    x.name.as_str(),
-                    x.force,
-                    x.arch.context("missing architecture argument (--arch)")?,
-                    x.os.context("missing operating system argument (--os)")?,
-                    x.dist.context("missing distribution argument (--dist)")?,
-                  )?;
+                  .publish_target(export_folder.to_str().unwrap(), x)?;
               }
             }
           }
@@ -127,6 +128,20 @@ fn try_main() -> anyhow::Result<()> {
           )
         }
       },
+      Command::Vendor(x) => {
+        puff
+          .sync()?
+          .vendor(x)?;
+      },
+      Command::Cache(x) => {
+        puff.cache(x)?;
+      },
+      Command::Env(x) => {
+        puff.env(x)?;
+      },
+      Command::Shell(x) => {
+        return Ok(puff.shell(x)?.code().unwrap_or(1));
+      },
       Command::Purge(x) => {
         let _ = puff
           .purge(x)
@@ -137,14 +152,18 @@ fn try_main() -> anyhow::Result<()> {
     None => {}
   }
 
-  Ok(())
+  Ok(0)
 }
 
 fn main() {
-  if let Err(e) = try_main() {
-    eprintln!("{}: {}",
-      "fatal error in puff".to_string().red().bold(),
-      e.to_string().bright_red().bold());
-    std::process::exit(1);
+  match try_main() {
+    Ok(0) => {},
+    Ok(code) => std::process::exit(code),
+    Err(e) => {
+      eprintln!("{}: {}",
+        "fatal error in puff".to_string().red().bold(),
+        e.to_string().bright_red().bold());
+      std::process::exit(1);
+    }
   }
 }
\ No newline at end of file
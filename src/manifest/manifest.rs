@@ -18,7 +18,14 @@ nest!
       pub license: Option<String>
     },
     pub needs: Option<HashMap<String, ManifestDependencyData>>,
-    pub build: Option<HashMap<String, VersionRange>>
+    pub build: Option<HashMap<String, VersionRange>>,
+    pub vendor: Option<pub struct ManifestSectionVendor {
+      pub path: String
+    }>,
+    pub pack: Option<pub struct ManifestSectionPack {
+      pub include: Option<Vec<String>>,
+      pub exclude: Option<Vec<String>>
+    }>
   }
 }
 
@@ -42,17 +49,10 @@ impl Manifest
     )
   }
 
-  pub fn from_tar_gz(tar_path: &str) -> anyhow::Result<Self> {
-    let tmp_dir = tempfile::tempdir()?;
-    let tmp_dir_path = tmp_dir.path();
-
-    crate::pack::unpack(
-      tar_path,
-      tmp_dir_path.to_str().context("failed to convert tmpdir path to str")?
-    )?;
-
-    let manifest = Self::from_directory(tmp_dir_path.to_str().context("failed to convert tmpdir path to str")?)?;
-    Ok(manifest)
+  pub fn from_tarball(tar_path: &str) -> anyhow::Result<Self> {
+    let manifest = crate::pack::read_entry(tar_path, crate::names::MANIFEST_FILE)?
+      .context(format!("manifest is not found in {}", tar_path))?;
+    Ok(toml::from_str(std::str::from_utf8(&manifest)?)?)
   }
 }
 
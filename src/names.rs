@@ -4,8 +4,24 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const MANIFEST_FILE: &str = "Puff.toml";
 pub const EXTENSIONS_DIRECTORY: &str = ".puff";
 pub const RECIPE_FILE: &str = "recipe.yml";
+pub const IGNORE_FILE: &str = ".puffignore";
 pub const DEPENDENCIES_FOLDER: &str = "dependencies";
 pub const TARGET_FOLDER: &str = "target";
 pub const EXPORT_FOLDER: &str = "export";
+pub const BUILT_FOLDER: &str = "built";
+pub const SOURCES_FOLDER: &str = "sources";
+pub const BUILD_METADATA_FILE: &str = "build.toml";
+pub const BUILD_LOG_FILE: &str = "puff-build.log";
+pub const PACKAGE_INDEX_FILE: &str = "index.toml";
+pub const VENDOR_FOLDER: &str = "vendor";
+/// Files generated for installed packages live here. Package names can't contain dots, so it never collides with a prefix
+pub const GENERATED_FOLDER: &str = ".puff";
+pub const MERGED_FOLDER: &str = "merged";
+pub const PREFIXES_FILE: &str = "prefixes.txt";
+pub const INSTALL_STATE_FILE: &str = ".puff-state.json";
+pub const CMAKE_INTEGRATION_FILE: &str = "puff.cmake";
+pub const CMAKE_USER_PRESETS_FILE: &str = "CMakeUserPresets.json";
+pub const CMAKE_PRESET_NAME: &str = "puff";
+pub const PKGCONFIG_FOLDER: &str = "pkgconfig";
 
-pub const PACKED_SOURCE_TARBALL_NAME: &str = "{name}-{version}-packed-sources.tar.gz";
\ No newline at end of file
+pub const PACKED_SOURCE_TARBALL_NAME: &str = "{name}-{version}-packed-sources.{ext}";
\ No newline at end of file
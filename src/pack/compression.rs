@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use flate2::GzBuilder;
+use crate::types::Compression;
+
+/// Compressing writer for any of supported formats. Must be finished explicitly
+pub enum Encoder<W: Write>
+{
+  Gzip(flate2::write::GzEncoder<W>),
+  Zstd(zstd::Encoder<'static, W>),
+  Xz(xz2::write::XzEncoder<W>)
+}
+
+impl<W: Write> Encoder<W>
+{
+  pub fn new(writer: W, compression: Compression) -> anyhow::Result<Self>
+  {
+    Ok(match compression {
+      Compression::Gzip => Self::Gzip(GzBuilder::new()
+        .mtime(0)
+        .operating_system(255)
+        .write(writer, flate2::Compression::default())),
+      Compression::Zstd => Self::Zstd(zstd::Encoder::new(writer, 0)?),
+      Compression::Xz => Self::Xz(xz2::write::XzEncoder::new(writer, 6))
+    })
+  }
+
+  pub fn finish(self) -> anyhow::Result<W>
+  {
+    Ok(match self {
+      Self::Gzip(x) => x.finish()?,
+      Self::Zstd(x) => x.finish()?,
+      Self::Xz(x) => x.finish()?
+    })
+  }
+}
+
+impl<W: Write> Write for Encoder<W>
+{
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>
+  {
+    match self {
+      Self::Gzip(x) => x.write(buf),
+      Self::Zstd(x) => x.write(buf),
+      Self::Xz(x) => x.write(buf)
+    }
+  }
+
+  fn flush(&mut self) -> std::io::Result<()>
+  {
+    match self {
+      Self::Gzip(x) => x.flush(),
+      Self::Zstd(x) => x.flush(),
+      Self::Xz(x) => x.flush()
+    }
+  }
+}
+
+/// Opens compressed archive for reading, detecting format from magic bytes
+pub fn decoder(path: &Path) -> anyhow::Result<Box<dyn Read>>
+{
+  let file = File::open(path)?;
+  Ok(match Compression::detect(path)? {
+    Compression::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+    Compression::Zstd => Box::new(zstd::Decoder::with_buffer(BufReader::new(file))?),
+    Compression::Xz => Box::new(xz2::read::XzDecoder::new(file))
+  })
+}
@@ -1,5 +1,9 @@
 mod pack;
+mod rules;
 mod unpack;
+mod compression;
 
 pub use pack::*;
-pub use unpack::unpack;
\ No newline at end of file
+pub use rules::PackRules;
+pub use unpack::{read_entry, unpack, unpack_stripped, unpack_tar};
+pub use compression::{decoder, Encoder};
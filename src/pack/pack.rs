@@ -1,73 +1,122 @@
 use std::collections::HashMap;
 use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use anyhow::Context;
 use colored::Colorize;
-use flate2::write::GzEncoder;
 use indicatif::ProgressBar;
 use crate::builder::Recipe;
 use crate::manifest::Manifest;
-use crate::names::PACKED_SOURCE_TARBALL_NAME;
-use crate::types::{Arch, Distribution, OperatingSystem, VersionRange};
-
-pub fn pack(source: &str, target: &str) -> anyhow::Result<()> {
-  let tar_gz = File::create(target)?;
-  let enc = GzEncoder::new(tar_gz, flate2::Compression::default());
-  let mut tar = tar::Builder::new(enc);
-
-  for entry in std::fs::read_dir(source)? {
-    let entry = entry?;
-    let path = entry.path();
-    if path.is_dir() {
-      let dirname = path.file_name().unwrap().to_os_string().into_string().unwrap();
-      if dirname != ".git"
-        && !dirname.contains("build")
-        && !dirname.contains("target")
-        && dirname != ".idea"
-        && dirname != target
-      {
-        tar.append_dir_all(dirname, path.clone())?;
-      }
-    }
+use crate::names::{EXTENSIONS_DIRECTORY, MANIFEST_FILE, PACKED_SOURCE_TARBALL_NAME, RECIPE_FILE};
+use crate::pack::{Encoder, PackRules};
+use crate::types::{Arch, Compression, Distribution, OperatingSystem, VersionRange};
+
+/// Modification time of packed entries when `SOURCE_DATE_EPOCH` is not set. Same as in `tar::HeaderMode::Deterministic`
+const DETERMINISTIC_MTIME: u64 = 1153704088;
 
-    if path.is_file() {
-      let filename = path.file_name().unwrap().to_os_string().into_string().unwrap();
-      if filename != target
-        && !filename.ends_with(".user")
-      {
-        tar.append_path_with_name(path.clone(), filename)?;
-      }
+/// Packs `source` into reproducible tarball: entries are sorted, ownership is zeroed, permissions and
+/// modification times are normalized, compression header is fixed
+pub fn pack(source: &str, target: &str, compression: Compression) -> anyhow::Result<()> {
+  let mut entries = PackRules::new(Path::new(source))?
+    .collect(Some(Path::new(target)))?;
+  // manifest and recipe go first, so they can be read without decompressing whole archive
+  let metadata = [
+    PathBuf::from(MANIFEST_FILE),
+    PathBuf::from(EXTENSIONS_DIRECTORY),
+    Path::new(EXTENSIONS_DIRECTORY).join(RECIPE_FILE)
+  ];
+  entries.sort_by_key(|x| metadata.iter().position(|y| y == x).unwrap_or(metadata.len()));
+  let mtime = std::env::var("SOURCE_DATE_EPOCH")
+    .ok()
+    .and_then(|x| x.trim().parse::<u64>().ok())
+    .unwrap_or(DETERMINISTIC_MTIME);
+  let mut tar = tar::Builder::new(Encoder::new(File::create(target)?, compression)?);
+
+  for entry in entries {
+    let path = Path::new(source).join(&entry);
+    let metadata = std::fs::metadata(&path)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_mtime(mtime);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_username("")?;
+    header.set_groupname("")?;
+    if metadata.is_dir() {
+      header.set_entry_type(tar::EntryType::Directory);
+      header.set_mode(0o755);
+      header.set_size(0);
+      tar.append_data(&mut header, &entry, std::io::empty())?;
+    } else {
+      header.set_entry_type(tar::EntryType::Regular);
+      header.set_mode(if is_executable(&metadata) { 0o755 } else { 0o644 });
+      header.set_size(metadata.len());
+      tar.append_data(&mut header, &entry, File::open(&path)?)?;
     }
   }
-  tar.finish()?;
+  tar
+    .into_inner()?
+    .finish()?;
   Ok(())
 }
 
-pub fn pack_with_manifest(path: &str) -> anyhow::Result<String>
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool
+{
+  use std::os::unix::fs::PermissionsExt;
+  metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool { false }
+
+pub fn pack_with_manifest(path: &str, compression: Compression, output: Option<&str>) -> anyhow::Result<String>
 {
   let manifest = Manifest::from_directory(path)?;
   let mut fmt: HashMap<String, String> = HashMap::new();
   fmt.insert("name".to_string(), manifest.this.name.clone());
   fmt.insert("version".to_string(), manifest.this.version.clone().to_string());
+  fmt.insert("ext".to_string(), compression.extension().to_string());
   let tar_name = strfmt::strfmt(PACKED_SOURCE_TARBALL_NAME, &fmt)
     .context("failed to format tarball name")?;
-  pack_into_data_folder(path, &tar_name, &manifest.this.name, manifest.this.version)
+  pack_into(path, &tar_name, &manifest.this.name, manifest.this.version, compression, output)
 }
 
-pub fn pack_for_cache(path: &str, arch: Arch, distribution: Distribution, os: OperatingSystem) -> anyhow::Result<String>
+pub fn pack_for_cache(path: &str, arch: Arch, distribution: Distribution, os: OperatingSystem, profile: Option<&str>) -> anyhow::Result<String>
+{
+  pack_target(path, arch, distribution, os, profile, Compression::default(), None)
+}
+
+pub fn pack_target(
+  path: &str,
+  arch: Arch,
+  distribution: Distribution,
+  os: OperatingSystem,
+  profile: Option<&str>,
+  compression: Compression,
+  output: Option<&str>
+) -> anyhow::Result<String>
 {
   let manifest = Manifest::from_directory(path)?;
-  let tar_name = format!("{}-{}-{}-{}-{}.tar.gz",
+  let tar_name = format!("{}-{}-{}-{}-{}.{}",
     &manifest.this.name,
     &manifest.this.version,
     arch.to_string(),
     os.to_string(),
-    distribution.to_string()
+    distribution.with_profile(profile),
+    compression.extension()
   );
-  pack_into_data_folder(path, &tar_name, &manifest.this.name, manifest.this.version)
+  pack_into(path, &tar_name, &manifest.this.name, manifest.this.version, compression, output)
 }
 
-fn pack_into_data_folder(path: &str, tar_name: &str, name: &str, version: VersionRange) -> anyhow::Result<String>
+/// Packs into `output` (file or existing directory) or into user data folder if no output is given
+fn pack_into(
+  path: &str,
+  tar_name: &str,
+  name: &str,
+  version: VersionRange,
+  compression: Compression,
+  output: Option<&str>
+) -> anyhow::Result<String>
 {
   let pb = ProgressBar::new_spinner();
   pb.enable_steady_tick(Duration::from_millis(100));
@@ -78,13 +127,17 @@ fn pack_into_data_folder(path: &str, tar_name: &str, name: &str, version: Versio
 
   let _ = Recipe::from_directory(path)?; // only for checking for it's existence
 
-  let cache_dir = directories::ProjectDirs::from("io", crate::names::NAME, crate::names::NAME)
-    .context("failed to get project directories")?
-    .data_dir()
-    .join("packed");
-  std::fs::create_dir_all(cache_dir.as_path())?;
-  let target_path = cache_dir.join(tar_name);
-  pack(path, target_path.to_str().unwrap())?;
+  let target_path = match output {
+    Some(x) if Path::new(x).is_dir() || x.ends_with('/') || x.ends_with('\\') => Path::new(x).join(tar_name),
+    Some(x) => PathBuf::from(x),
+    None => directories::ProjectDirs::from("io", crate::names::NAME, crate::names::NAME)
+      .context("failed to get project directories")?
+      .data_dir()
+      .join("packed")
+      .join(tar_name)
+  };
+  std::fs::create_dir_all(target_path.parent().context("invalid output path")?)?;
+  pack(path, target_path.to_str().unwrap(), compression)?;
 
   pb.finish_with_message(format!("{} {}@{}",
                                  "successfully packed".to_string().green().bold(),
@@ -93,4 +146,70 @@ fn pack_into_data_folder(path: &str, tar_name: &str, name: &str, version: Versio
   ));
 
   Ok(target_path.into_os_string().into_string().unwrap())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_pack_is_reproducible() {
+    let source = tempfile::tempdir().unwrap();
+    let target = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(source.path().join("include")).unwrap();
+    std::fs::write(source.path().join("include/a.h"), b"#pragma once").unwrap();
+    std::fs::write(source.path().join("b.txt"), b"b").unwrap();
+
+    let first = target.path().join("first.tar.gz");
+    pack(source.path().to_str().unwrap(), first.to_str().unwrap(), Compression::Gzip).unwrap();
+    File::options()
+      .write(true)
+      .open(source.path().join("b.txt"))
+      .unwrap()
+      .set_modified(std::time::SystemTime::now() + Duration::from_secs(3600))
+      .unwrap();
+    let second = target.path().join("second.tar.gz");
+    pack(source.path().to_str().unwrap(), second.to_str().unwrap(), Compression::Gzip).unwrap();
+
+    assert_eq!(std::fs::read(first).unwrap(), std::fs::read(second).unwrap());
+  }
+
+  #[test]
+  fn test_pack_and_unpack_all_formats() {
+    let source = tempfile::tempdir().unwrap();
+    std::fs::write(source.path().join("a.txt"), b"contents").unwrap();
+
+    for compression in [Compression::Gzip, Compression::Zstd, Compression::Xz] {
+      let target = tempfile::tempdir().unwrap();
+      let archive = target.path().join("archive.tar");
+      pack(source.path().to_str().unwrap(), archive.to_str().unwrap(), compression).unwrap();
+      assert_eq!(Compression::detect(&archive).unwrap(), compression);
+
+      let unpacked = target.path().join("unpacked");
+      crate::pack::unpack(archive.to_str().unwrap(), unpacked.to_str().unwrap()).unwrap();
+      assert_eq!(std::fs::read(unpacked.join("a.txt")).unwrap(), b"contents");
+    }
+  }
+
+  #[test]
+  fn test_manifest_is_first_entry() {
+    let source = tempfile::tempdir().unwrap();
+    let target = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(source.path().join(".puff")).unwrap();
+    std::fs::write(source.path().join(".puff/recipe.yml"), b"static: ~").unwrap();
+    std::fs::write(source.path().join("CMakeLists.txt"), b"").unwrap();
+    std::fs::write(source.path().join("Puff.toml"), b"[this]\nname = \"a\"\nversion = \"1.0.0\"\n").unwrap();
+
+    let archive = target.path().join("a.tar.gz");
+    pack(source.path().to_str().unwrap(), archive.to_str().unwrap(), Compression::Gzip).unwrap();
+    let mut tar = tar::Archive::new(crate::pack::decoder(&archive).unwrap());
+    let first = tar.entries().unwrap().next().unwrap().unwrap().path().unwrap().to_path_buf();
+    assert_eq!(first, PathBuf::from(MANIFEST_FILE));
+    assert_eq!(Manifest::from_tarball(archive.to_str().unwrap()).unwrap().this.name, "a");
+    assert_eq!(
+      crate::pack::read_entry(archive.to_str().unwrap(), ".puff/recipe.yml").unwrap(),
+      Some(b"static: ~".to_vec())
+    );
+  }
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use crate::manifest::Manifest;
+use crate::names::{EXTENSIONS_DIRECTORY, IGNORE_FILE, MANIFEST_FILE, RECIPE_FILE};
+
+/// Patterns applied to every packed tree. Can be overridden with `!pattern` in `.puffignore`
+const DEFAULT_IGNORE: [&str; 5] = [".git/", ".idea/", "/build/", "/target/", "*.user"];
+
+/// Decides which files go into a tarball, following gitignore rules from `.puffignore` files
+/// and optional `[pack]` section of manifest
+pub struct PackRules
+{
+  root: PathBuf,
+  defaults: Gitignore,
+  include: Option<Gitignore>,
+  exclude: Option<Gitignore>
+}
+
+impl PackRules
+{
+  pub fn new(root: &Path) -> anyhow::Result<Self>
+  {
+    let section = match root.join(MANIFEST_FILE).exists() {
+      true => Manifest::from_directory(root.to_str().unwrap())?.pack,
+      false => None
+    };
+    Ok(Self
+    {
+      root: root.to_path_buf(),
+      defaults: Self::build(root, DEFAULT_IGNORE.iter().map(|x| x.to_string()))?,
+      include: match section.as_ref().and_then(|x| x.include.clone()) {
+        Some(x) => Some(Self::build(root, x.into_iter())?),
+        None => None
+      },
+      exclude: match section.as_ref().and_then(|x| x.exclude.clone()) {
+        Some(x) => Some(Self::build(root, x.into_iter())?),
+        None => None
+      }
+    })
+  }
+
+  /// Returns paths relative to root of all directories and files that should be packed, in sorted order
+  pub fn collect(&self, skip: Option<&Path>) -> anyhow::Result<Vec<PathBuf>>
+  {
+    let mut entries = Vec::new();
+    let mut stack = Vec::new();
+    self.walk(&self.root, skip, &mut stack, &mut entries)?;
+    Ok(entries)
+  }
+
+  fn walk(&self, directory: &Path, skip: Option<&Path>, stack: &mut Vec<Gitignore>, entries: &mut Vec<PathBuf>) -> anyhow::Result<()>
+  {
+    let ignore_file = directory.join(IGNORE_FILE);
+    let pushed = ignore_file.exists();
+    if pushed {
+      let mut builder = GitignoreBuilder::new(directory);
+      if let Some(e) = builder.add(&ignore_file) {
+        return Err(anyhow::anyhow!("failed to parse {} ({})", ignore_file.display(), e));
+      }
+      stack.push(builder.build()?);
+    }
+
+    let mut children = std::fs::read_dir(directory)?
+      .map(|x| x.map(|y| y.path()))
+      .collect::<Result<Vec<_>, _>>()?;
+    children.sort();
+    for path in children {
+      if skip.is_some_and(|x| x == path) {
+        continue;
+      }
+      let is_dir = path.is_dir();
+      let relative = path.strip_prefix(&self.root)?.to_path_buf();
+      let required = Self::is_required(&relative);
+      if !required && !self.is_packed(&path, is_dir, stack) {
+        continue;
+      }
+      if is_dir {
+        // Directory is kept only if something under it was collected
+        let position = entries.len();
+        entries.push(relative);
+        self.walk(&path, skip, stack, entries)?;
+        if entries.len() == position + 1 {
+          entries.pop();
+        }
+      } else if path.is_file() && (required || self.include
+        .as_ref()
+        .is_none_or(|x| x.matched_path_or_any_parents(&relative, false).is_ignore()))
+      {
+        entries.push(relative);
+      }
+    }
+
+    if pushed {
+      stack.pop();
+    }
+    Ok(())
+  }
+
+  /// Manifest and recipe are packed regardless of rules, package can't be read without them
+  fn is_required(relative: &Path) -> bool
+  {
+    relative == Path::new(MANIFEST_FILE) || Path::new(EXTENSIONS_DIRECTORY).join(RECIPE_FILE).starts_with(relative)
+  }
+
+  fn is_packed(&self, path: &Path, is_dir: bool, stack: &[Gitignore]) -> bool
+  {
+    if let Some(exclude) = &self.exclude {
+      if exclude.matched(path, is_dir).is_ignore() {
+        return false;
+      }
+    }
+    for x in stack.iter().rev().chain(std::iter::once(&self.defaults)) {
+      let matched = x.matched(path, is_dir);
+      if matched.is_ignore() { return false; }
+      if matched.is_whitelist() { return true; }
+    }
+    true
+  }
+
+  fn build(root: &Path, patterns: impl Iterator<Item = String>) -> anyhow::Result<Gitignore>
+  {
+    let mut builder = GitignoreBuilder::new(root);
+    for x in patterns {
+      builder.add_line(None, x.as_str())?;
+    }
+    Ok(builder.build()?)
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  fn touch(root: &Path, path: &str)
+  {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, b"").unwrap();
+  }
+
+  #[test]
+  fn test_collect() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    touch(root, "Puff.toml");
+    touch(root, "buildtools/gen.py");
+    touch(root, "targets/arm.cmake");
+    touch(root, "build/CMakeCache.txt");
+    touch(root, "target/export/lib.a");
+    touch(root, ".git/HEAD");
+    touch(root, "CMakeLists.txt.user");
+    touch(root, "src/main.cpp");
+    touch(root, "src/generated/out.cpp");
+    touch(root, "src/.puffignore");
+    std::fs::write(root.join("src/.puffignore"), "generated/\n").unwrap();
+    std::fs::write(root.join("Puff.toml"), "[this]\nname = \"a\"\nversion = \"1.0.0\"\n[pack]\nexclude = [\"*.py\"]\n").unwrap();
+
+    let files = PackRules::new(root)
+      .unwrap()
+      .collect(None)
+      .unwrap()
+      .into_iter()
+      .map(|x| x.to_str().unwrap().replace('\\', "/"))
+      .collect::<Vec<_>>();
+    assert_eq!(files, vec![
+      "Puff.toml",
+      "src",
+      "src/.puffignore",
+      "src/main.cpp",
+      "targets",
+      "targets/arm.cmake"
+    ]);
+  }
+
+  #[test]
+  fn test_manifest_and_recipe_are_always_packed() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    touch(root, ".puff/recipe.yml");
+    touch(root, ".puff/notes.txt");
+    touch(root, "docs/readme.md");
+    touch(root, "src/main.cpp");
+    std::fs::write(root.join(".puffignore"), "Puff.toml\n.puff/\n").unwrap();
+    std::fs::write(root.join("Puff.toml"), "[this]\nname = \"a\"\nversion = \"1.0.0\"\n[pack]\ninclude = [\"src/**\"]\n").unwrap();
+
+    let files = PackRules::new(root)
+      .unwrap()
+      .collect(None)
+      .unwrap()
+      .into_iter()
+      .map(|x| x.to_str().unwrap().replace('\\', "/"))
+      .collect::<Vec<_>>();
+    assert_eq!(files, vec![
+      ".puff",
+      ".puff/recipe.yml",
+      "Puff.toml",
+      "src",
+      "src/main.cpp"
+    ]);
+  }
+}
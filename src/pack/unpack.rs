@@ -1,11 +1,216 @@
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{bail, ensure};
+use crate::pack::decoder;
+
+/// Maximum total size of files extracted from a single archive
+pub const UNPACKED_SIZE_LIMIT: u64 = 64 * 1024 * 1024 * 1024;
+
+/// Unpacks compressed tarball. Entries escaping destination folder are skipped and reported,
+/// setuid/setgid bits are stripped and total extracted size is capped by `UNPACKED_SIZE_LIMIT`
 pub fn unpack(from: &str, to: &str) -> anyhow::Result<()> {
+  unpack_from(decoder(Path::new(from))?, from, to, 0)
+}
+
+/// Same as `unpack`, but drops `strip` leading components from entry paths, as `tar --strip-components` does.
+/// Entries not deeper than `strip` are skipped
+pub fn unpack_stripped(from: &str, to: &str, strip: usize) -> anyhow::Result<()> {
+  unpack_from(decoder(Path::new(from))?, from, to, strip)
+}
+
+/// Same as `unpack`, but for uncompressed tar archives
+pub fn unpack_tar(from: &str, to: &str) -> anyhow::Result<()> {
+  unpack_from(std::fs::File::open(from)?, from, to, 0)
+}
+
+/// Reads a single file from compressed tarball without unpacking it. Stops at first matching entry
+pub fn read_entry(from: &str, file: &str) -> anyhow::Result<Option<Vec<u8>>> {
+  let mut archive = tar::Archive::new(decoder(Path::new(from))?);
+  for entry in archive.entries()? {
+    let mut entry = entry?;
+    if normalize(Path::new(""), &entry.path()?) != Some(PathBuf::from(file)) {
+      continue;
+    }
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    return Ok(Some(data));
+  }
+  Ok(None)
+}
+
+fn unpack_from<R: Read>(reader: R, name: &str, to: &str, strip: usize) -> anyhow::Result<()> {
   std::fs::create_dir_all(to)?;
+  let destination = Path::new(to).canonicalize()?;
 
-  let tar_gz = std::fs::File::open(from)?;
-  let tar = flate2::read::GzDecoder::new(tar_gz);
-  let mut archive = tar::Archive::new(tar);
-  archive.unpack(to)?;
+  let mut archive = tar::Archive::new(reader);
+  archive.set_mask(0o7000);
+  archive.set_unpack_xattrs(false);
 
+  let mut violations: Vec<String> = Vec::new();
+  let mut total: u64 = 0;
+  for entry in archive.entries()? {
+    let mut entry = entry?;
+    let path = entry.path()?.to_path_buf();
+    let Some(relative) = strip_components(&path, strip) else {
+      violations.push(format!("{}: path escapes destination", path.display()));
+      continue;
+    };
+    if strip > 0 && relative.as_os_str().is_empty() {
+      continue;
+    }
+    if let Err(e) = check_entry(&entry, &relative, strip) {
+      violations.push(format!("{}: {}", path.display(), e));
+      continue;
+    }
+    total += entry.header().size()?;
+    ensure!(total <= UNPACKED_SIZE_LIMIT, "archive {} exceeds unpacked size limit of {} bytes", name, UNPACKED_SIZE_LIMIT);
+    if strip == 0 {
+      entry.unpack_in(&destination)?;
+      continue;
+    }
+
+    // `unpack_in` only knows original paths, so stripped entries get the same parent check here
+    let target = destination.join(&relative);
+    if let Some(parent) = target.parent() {
+      std::fs::create_dir_all(parent)?;
+      ensure!(parent.canonicalize()?.starts_with(&destination), "{} escapes destination through symlink", path.display());
+    }
+    match entry.header().entry_type().is_hard_link() {
+      true => {
+        let link = entry
+          .link_name()?
+          .and_then(|x| strip_components(&x, strip))
+          .ok_or(anyhow::anyhow!("link without target"))?;
+        std::fs::hard_link(destination.join(link), &target)?;
+      },
+      false => { entry.unpack(&target)?; }
+    }
+  }
+
+  if !violations.is_empty() {
+    bail!("refused to unpack {} entries from {}:\n  {}", violations.len(), name, violations.join("\n  "));
+  }
   Ok(())
 }
 
+/// Checks entry at `root`, its normalized and stripped path
+fn check_entry<R: Read>(entry: &tar::Entry<R>, root: &Path, strip: usize) -> anyhow::Result<()>
+{
+  ensure!(root.components().count() > 0 || entry.header().entry_type().is_dir(), "empty path");
+
+  let kind = entry.header().entry_type();
+  if kind.is_symlink() || kind.is_hard_link() {
+    let link = entry
+      .link_name()?
+      .ok_or(anyhow::anyhow!("link without target"))?
+      .to_path_buf();
+    // symlinks are relative to their own folder, hardlinks are relative to archive root and are stripped too
+    let target = match kind.is_symlink() {
+      true => normalize(root.parent().unwrap_or(Path::new("")), &link),
+      false => strip_components(&link, strip).filter(|x| !x.as_os_str().is_empty())
+    };
+    ensure!(target.is_some(), "link target {} escapes destination", link.display());
+  } else if kind.is_character_special() || kind.is_block_special() || kind.is_fifo() {
+    bail!("special files are not allowed");
+  }
+  Ok(())
+}
+
+/// Normalizes `path` and drops its `strip` leading components. `None` if path escapes archive root
+fn strip_components(path: &Path, strip: usize) -> Option<PathBuf>
+{
+  normalize(Path::new(""), path).map(|x| x.components().skip(strip).collect())
+}
+
+/// Lexically joins `path` to `base`, returning `None` if result is absolute or leaves `base`'s root
+fn normalize(base: &Path, path: &Path) -> Option<PathBuf>
+{
+  let mut result = base.to_path_buf();
+  for component in path.components() {
+    match component {
+      Component::Normal(x) => result.push(x),
+      Component::CurDir => (),
+      Component::ParentDir => if !result.pop() { return None; },
+      Component::RootDir | Component::Prefix(_) => return None
+    }
+  }
+  Some(result)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  fn archive(entries: &[(&str, tar::EntryType, Option<&str>)]) -> Vec<u8>
+  {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, kind, link) in entries {
+      let mut header = tar::Header::new_gnu();
+      header.set_entry_type(*kind);
+      header.set_mode(0o4755);
+      header.set_size(0);
+      // bypass path validation of `set_path` to produce malicious entries
+      let name = &mut header.as_old_mut().name;
+      name[..path.len()].copy_from_slice(path.as_bytes());
+      if let Some(link) = link {
+        header.set_link_name(link).unwrap();
+      }
+      header.set_cksum();
+      builder.append(&header, std::io::empty()).unwrap();
+    }
+    builder.into_inner().unwrap()
+  }
+
+  #[test]
+  fn test_rejects_escaping_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = archive(&[
+      ("include/a.h", tar::EntryType::Regular, None),
+      ("../evil.sh", tar::EntryType::Regular, None),
+      ("/etc/evil", tar::EntryType::Regular, None),
+      ("lib/link.so", tar::EntryType::Symlink, Some("liba.so.1")),
+      ("lib/escape", tar::EntryType::Symlink, Some("../../.bashrc")),
+      ("hard", tar::EntryType::Link, Some("../outside")),
+    ]);
+    let result = unpack_from(data.as_slice(), "test", dir.path().to_str().unwrap(), 0);
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("refused to unpack 4 entries"), "{}", error);
+    assert!(dir.path().join("include/a.h").exists());
+    assert!(dir.path().join("lib/link.so").symlink_metadata().is_ok());
+    assert!(dir.path().join("lib/escape").symlink_metadata().is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_strips_setuid() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let data = archive(&[("bin/tool", tar::EntryType::Regular, None)]);
+    unpack_from(data.as_slice(), "test", dir.path().to_str().unwrap(), 0).unwrap();
+    let mode = std::fs::metadata(dir.path().join("bin/tool")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7000, 0);
+  }
+
+  #[test]
+  fn test_strips_components() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = archive(&[
+      ("zlib-1.3", tar::EntryType::Directory, None),
+      ("zlib-1.3/README", tar::EntryType::Regular, None),
+      ("zlib-1.3/src/zlib.h", tar::EntryType::Regular, None),
+      ("zlib-1.3/src/zconf.h", tar::EntryType::Link, Some("zlib-1.3/src/zlib.h")),
+      ("zlib-1.3/include", tar::EntryType::Symlink, Some("src")),
+      ("zlib-1.3/escape", tar::EntryType::Symlink, Some("../other/file")),
+      ("zlib-1.3/../../evil.sh", tar::EntryType::Regular, None),
+    ]);
+    let result = unpack_from(data.as_slice(), "test", dir.path().to_str().unwrap(), 1);
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("refused to unpack 2 entries"), "{}", error);
+    assert!(dir.path().join("README").is_file());
+    assert!(dir.path().join("src/zconf.h").is_file());
+    assert!(dir.path().join("include/zlib.h").is_file());
+    assert!(dir.path().join("escape").symlink_metadata().is_err());
+    assert!(!dir.path().join("zlib-1.3").exists());
+  }
+}
@@ -1,13 +1,19 @@
 use std::cell::RefCell;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use anyhow::Context;
+use anyhow::{bail, ensure, Context};
+use indicatif::HumanBytes;
 use colored::Colorize;
 use crate::core;
-use crate::core::args::{InstallArgs, PurgeArgs};
-use crate::names::DEPENDENCIES_FOLDER;
-use crate::resolver::{Resolver};
-use crate::types::{Arch, Distribution, OperatingSystem};
+use crate::core::args::{CacheCommand, EnvArgs, InstallArgs, PackArgs, PublishArgs, PurgeArgs, ShellArgs, VendorArgs};
+use crate::integration::env::ActivationEnvironment;
+use crate::manifest::Manifest;
+use crate::names::{DEPENDENCIES_FOLDER, EXPORT_FOLDER, MANIFEST_FILE, TARGET_FOLDER, VENDOR_FOLDER};
+use crate::builder::BuildProfile;
+use crate::resolver::{Dependency, Resolver};
+use crate::types::{Arch, Compression, Distribution, OperatingSystem, Shell};
+use crate::utility::checksum::md5_file;
+use crate::vendor::Vendor;
 
 pub struct Puff
 {
@@ -34,19 +40,63 @@ impl Puff
     })
   }
 
-  pub fn pack(&self, path: &str) -> anyhow::Result<Option<String>> {
-    Ok(Some(crate::pack::pack_with_manifest(path)?))
+  pub fn pack(&self, path: &str, compression: Compression) -> anyhow::Result<Option<String>> {
+    Ok(Some(crate::pack::pack_with_manifest(path, compression, None)?))
   }
 
-  pub fn publish_target(
-    &self,
-    path: &str,
-    registry_name: &str,
-    force: bool,
-    arch: Arch,
-    os: OperatingSystem,
-    distribution: Distribution
-  ) -> anyhow::Result<&Self>
+  pub fn pack_with_args(&self, arguments: &PackArgs) -> anyhow::Result<&Self>
+  {
+    let folder = match &arguments.folder {
+      Some(x) => PathBuf::from(x),
+      None => std::env::current_dir()?,
+    };
+    let (path, distribution) = match arguments.dist {
+      Some(x @ (Distribution::Static | Distribution::Shared)) => {
+        let export_folder = folder.join(TARGET_FOLDER).join(EXPORT_FOLDER);
+        ensure!(export_folder.exists(), "target folder does not exist. run 'puff build' first");
+        (export_folder, Some(x))
+      },
+      _ => (folder, None)
+    };
+    let path = path.to_str().context("failed to convert path to string")?;
+
+    if arguments.list {
+      for x in crate::pack::PackRules::new(Path::new(path))?.collect(None)? {
+        if !Path::new(path).join(&x).is_dir() {
+          println!("{}", x.display());
+        }
+      }
+      return Ok(self);
+    }
+
+    let packed = match distribution {
+      Some(x) => crate::pack::pack_target(
+        path,
+        arguments.arch.unwrap_or(self.env.arch),
+        x,
+        arguments.os.unwrap_or(self.env.os),
+        self.profile_tag(arguments.profile.as_deref())?.as_deref(),
+        arguments.compression,
+        arguments.output.as_deref()
+      )?,
+      None => crate::pack::pack_with_manifest(path, arguments.compression, arguments.output.as_deref())?
+    };
+    println!("{}: {}", "path".bold(), packed.cyan());
+    println!("{}: {}", "size".bold(), HumanBytes(std::fs::metadata(&packed)?.len()).to_string().yellow());
+    println!("{}: {}", "md5".bold(), md5_file(Path::new(&packed))?.dimmed());
+    Ok(self)
+  }
+
+  /// Validates build profile name. Returns profile as recorded in package identity
+  fn profile_tag(&self, profile: Option<&str>) -> anyhow::Result<Option<String>>
+  {
+    Ok(match profile {
+      Some(x) => BuildProfile::new(x, &self.config)?.tag(),
+      None => None
+    })
+  }
+
+  pub fn publish_target(&self, path: &str, arguments: &PublishArgs) -> anyhow::Result<&Self>
   {
     let remotes_ref = self
       .remotes
@@ -54,22 +104,31 @@ impl Puff
     let remote = remotes_ref
       .remotes
       .iter()
-      .find(|x| x.name == registry_name)
-      .context(format!("registry {} not found", registry_name))?;
+      .find(|x| x.name == arguments.name)
+      .context(format!("registry {} not found", arguments.name))?;
+    let compression = arguments.compression.unwrap_or(remote.compression);
 
-    remote.push(
-      path,
-      self.pack(path)?.as_ref().context("failed to pack sources. contact the maintainer")?,
-      distribution,
-      arch,
-      os,
-      force
+    let manifest = Manifest::from_directory(path)?;
+    let dependency = Dependency::new(
+      manifest.this.name,
+      manifest.this.version,
+      arguments.arch.context("missing architecture argument (--arch)")?,
+      arguments.os.context("missing operating system argument (--os)")?,
+      arguments.dist.context("missing distribution argument (--dist)")?
+    ).with_profile(self.profile_tag(arguments.profile.as_deref())?);
+    let pushed = remote.push(
+      self.pack(path, compression)?.as_ref().context("failed to pack sources. contact the maintainer")?,
+      &dependency,
+      arguments.force
     )?;
+    if !pushed {
+      println!("{}: use --force flag to push anyway", String::from("tip").cyan().bold());
+    }
 
     Ok(self)
   }
 
-  pub fn publish_sources(&self, path: &str, registry_name: &str, force: bool) -> anyhow::Result<&Self>
+  pub fn publish_sources(&self, path: &str, registry_name: &str, force: bool, compression: Option<Compression>) -> anyhow::Result<&Self>
   {
     let remotes_ref = self
       .remotes
@@ -79,15 +138,24 @@ impl Puff
       .iter()
       .find(|x| x.name == registry_name)
       .context(format!("registry {} not found", registry_name))?;
+    let compression = compression.unwrap_or(remote.compression);
 
-    remote.push(
-      path,
-      self.pack(path)?.as_ref().context("failed to pack sources. contact the maintainer")?,
-      Distribution::Sources,
+    let manifest = Manifest::from_directory(path)?;
+    let dependency = Dependency::new(
+      manifest.this.name,
+      manifest.this.version,
       Arch::Unknown,
       OperatingSystem::Unknown,
+      Distribution::Sources
+    );
+    let pushed = remote.push(
+      self.pack(path, compression)?.as_ref().context("failed to pack sources. contact the maintainer")?,
+      &dependency,
       force
     )?;
+    if !pushed {
+      println!("{}: use --force flag to push anyway", String::from("tip").cyan().bold());
+    }
 
     Ok(self)
   }
@@ -126,11 +194,24 @@ impl Puff
       self.env.clone(),
       self.remotes.clone(),
       self.cache.clone(),
-      arguments.source_only
-    );
+      arguments.source_only,
+      arguments.upload_built
+        .clone()
+        .or(self.config.registry.upload_built.clone())
+    )
+      .with_vendor(self.vendor_for(path.as_str())?)
+      .with_merged_view(arguments.merged || self.config.install.merged)
+      .with_verbose(arguments.verbose)
+      .with_jobs(arguments.jobs.or(self.config.install.jobs).unwrap_or(1))
+      .with_profile(match arguments.profile.as_ref().or(self.config.install.profile.as_ref()) {
+        Some(x) => BuildProfile::new(x, &self.config)?,
+        None => BuildProfile::default()
+      });
 
     resolver
       .resolve(path.as_str())?;
+    crate::integration::cmake::generate(path.as_str(), &self.env)?;
+    crate::integration::pkgconfig::generate(path.as_str())?;
     std::fs::write(
       [path.as_str(), DEPENDENCIES_FOLDER, ".gitignore"].iter().collect::<PathBuf>(),
       "*\n!.gitignore"
@@ -139,6 +220,158 @@ impl Puff
     Ok(self)
   }
 
+  pub fn vendor(&self, arguments: &VendorArgs) -> anyhow::Result<&Self>
+  {
+    let path = match &arguments.path {
+      Some(x) => x.clone(),
+      None => std::env::current_dir()?.into_os_string().into_string().unwrap(),
+    };
+    let manifest = Manifest::from_directory(path.as_str())?;
+    let directory = arguments.directory
+      .clone()
+      .or(manifest.vendor.as_ref().map(|x| x.path.clone()))
+      .unwrap_or(String::from(VENDOR_FOLDER));
+    let oses = arguments.os
+      .clone()
+      .filter(|x| !x.is_empty())
+      .unwrap_or(vec![self.env.os]);
+    let arches = arguments.arch
+      .clone()
+      .filter(|x| !x.is_empty())
+      .unwrap_or(vec![self.env.arch]);
+
+    let mut tarballs: Vec<PathBuf> = Vec::new();
+    for os in &oses {
+      for arch in &arches {
+        let resolver = Resolver::new(
+          self.config.clone(),
+          Rc::new(core::Environment { os: *os, arch: *arch, target: None }),
+          self.remotes.clone(),
+          self.cache.clone(),
+          false,
+          None
+        );
+        for x in resolver.collect_recursively(manifest.clone())? {
+          if x.require_build || !arguments.no_binaries {
+            tarballs.push(x.tar_path.clone());
+          }
+          if !x.require_build && (arguments.sources || arguments.no_binaries) {
+            tarballs.push(resolver.try_get_sources(&x.dependency)?);
+          }
+        }
+      }
+    }
+    tarballs.sort();
+    tarballs.dedup();
+
+    Vendor::create(&Path::new(path.as_str()).join(directory), &tarballs)?;
+    Ok(self)
+  }
+
+  /// Returns vendor directory configured in project manifest, if any
+  pub fn vendor_for(&self, path: &str) -> anyhow::Result<Option<Rc<Vendor>>>
+  {
+    match Manifest::from_directory(path)?.vendor {
+      Some(x) => Ok(Vendor::open_if_exists(&Path::new(path).join(x.path))?.map(Rc::new)),
+      None => Ok(None)
+    }
+  }
+
+  pub fn cache(&self, command: &CacheCommand) -> anyhow::Result<&Self>
+  {
+    match command {
+      CacheCommand::Export(x) => {
+        let path = match &x.manifest {
+          Some(x) if x.ends_with(MANIFEST_FILE) => Path::new(x)
+            .parent()
+            .context("invalid manifest path")?
+            .to_path_buf(),
+          Some(x) if Path::new(x).extension().is_some_and(|y| y == "lock") => {
+            bail!("lockfiles are not supported, pass {} or project folder to --for instead", MANIFEST_FILE)
+          },
+          Some(x) if Path::new(x).is_file() => bail!("{} is not a {} manifest", x, MANIFEST_FILE),
+          Some(x) => PathBuf::from(x),
+          None => std::env::current_dir()?,
+        };
+        let manifest = Manifest::from_directory(path.to_str().context("failed to convert path to string")?)?;
+        let resolver = Resolver::new(
+          self.config.clone(),
+          Rc::new(core::Environment {
+            os: x.os.unwrap_or(self.env.os),
+            arch: x.arch.unwrap_or(self.env.arch),
+            target: None
+          }),
+          self.remotes.clone(),
+          self.cache.clone(),
+          false,
+          None
+        );
+        let mut tarballs = Vec::new();
+        for entry in resolver.collect_recursively(manifest)? {
+          if entry.require_build {
+            tarballs.extend(self.cache.built_for(&entry.dependency)?);
+          }
+          tarballs.push(entry.tar_path);
+        }
+        tarballs.sort();
+        tarballs.dedup();
+
+        self.cache.export(&tarballs, x.output.as_str())?;
+        println!("exported {} packages to {}",
+          tarballs.len().to_string().bold().green(),
+          x.output.dimmed()
+        );
+      },
+      CacheCommand::Import(x) => {
+        let count = self.cache.import(x.bundle.as_str())?;
+        println!("imported {} packages from {}",
+          count.to_string().bold().green(),
+          x.bundle.dimmed()
+        );
+      }
+    }
+    Ok(self)
+  }
+
+  pub fn env(&self, arguments: &EnvArgs) -> anyhow::Result<&Self>
+  {
+    let environment = Self::activation_environment(arguments.folder.clone())?;
+    match arguments.json {
+      true => println!("{}", environment.to_json()?),
+      false => print!("{}", environment.to_script(arguments.shell.unwrap_or(Shell::from_env())))
+    }
+    Ok(self)
+  }
+
+  /// Returns exit status of the subshell, which is the one of last command typed by user, not a puff failure
+  pub fn shell(&self, arguments: &ShellArgs) -> anyhow::Result<std::process::ExitStatus>
+  {
+    let environment = Self::activation_environment(arguments.folder.clone())?;
+    let program = std::env::var("SHELL").unwrap_or(String::from(match cfg!(windows) {
+      true => "powershell",
+      false => "sh"
+    }));
+    println!("starting {} with dependencies activated, exit to return", program.bold().green());
+    std::process::Command::new(&program)
+      .envs(environment.values()?)
+      .env("PUFF_SHELL", "1")
+      .status()
+      .context(format!("failed to start {}", program))
+  }
+
+  fn activation_environment(folder: Option<String>) -> anyhow::Result<ActivationEnvironment>
+  {
+    let path = match folder {
+      Some(x) => x,
+      None => std::env::current_dir()?.into_os_string().into_string().unwrap(),
+    };
+    ensure!(
+      Path::new(path.as_str()).join(DEPENDENCIES_FOLDER).exists(),
+      "dependencies are not installed in {}, run puff install first", path
+    );
+    ActivationEnvironment::new(path.as_str())
+  }
+
   pub fn purge(&self, args: &PurgeArgs) -> anyhow::Result<&Self>
   {
     if args.config || args.all {
@@ -157,6 +390,15 @@ impl Puff
         println!("{} directory does not exist", "cache".to_string().magenta().bold());
       }
     }
+    if args.store || args.all {
+      let store = crate::store::Store::new(&self.config);
+      if store.path.exists() {
+        println!("purging {} directory", "store".to_string().cyan().bold());
+        std::fs::remove_dir_all(&store.path)?;
+      } else {
+        println!("{} directory does not exist", "store".to_string().cyan().bold());
+      }
+    }
     Ok(self)
   }
 }
\ No newline at end of file
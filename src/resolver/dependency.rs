@@ -11,14 +11,17 @@ pub struct Dependency
   pub version: VersionRange,
   pub arch: Arch,
   pub os: OperatingSystem,
-  pub distribution: Distribution
+  pub distribution: Distribution,
+
+  /// Build profile, `None` for default release profile
+  pub profile: Option<String>
 }
 
 impl Display for Dependency
 {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
   {
-    write!(f, "{}@{}/{}/{}/{}", self.name, self.version, self.arch, self.os, self.distribution)
+    write!(f, "{}@{}/{}/{}/{}", self.name, self.version, self.arch, self.os, self.distribution_tag())
   }
 }
 
@@ -32,7 +35,29 @@ impl Dependency
       version,
       arch,
       os,
-      distribution
+      distribution,
+      profile: None
+    }
+  }
+
+  pub fn with_profile(mut self, profile: Option<String>) -> Self
+  {
+    self.profile = profile;
+    self
+  }
+
+  /// Distribution with build profile, as used in package file names
+  pub fn distribution_tag(&self) -> String
+  {
+    self.distribution.with_profile(self.profile.as_deref())
+  }
+
+  /// Folder of the package inside `dependencies`. Build profile is appended, so that profiles coexist
+  pub fn prefix_name(&self) -> String
+  {
+    match &self.profile {
+      Some(x) => format!("{}-{}", self.name, x),
+      None => self.name.clone()
     }
   }
 
@@ -46,12 +71,9 @@ impl Dependency
     let captures_version = re_version
       .captures(package_name)
       .context("invalid package name")?;
-    let re_arch = regex::Regex::new(r"(-([a-zA-Z0-9_]+)-([a-zA-Z0-9]+))")?;
-    let captures_arch = re_arch
-      .captures(package_name)
-      .context("invalid package name")?;
-    let re_distribution = regex::Regex::new(r"-([a-zA-Z0-9]+)\.tar")?;
-    let captures_distribution = re_distribution
+    // arch-os-distribution[-profile].tar
+    let re_target = regex::Regex::new(r"-([a-zA-Z0-9_]+)-([a-zA-Z0-9]+)-([a-zA-Z0-9]+)(-([a-zA-Z0-9_]+))?\.tar")?;
+    let captures_target = re_target
       .captures(package_name)
       .context("invalid package name")?;
 
@@ -59,9 +81,10 @@ impl Dependency
     {
       name: captures_name.get(1).unwrap().as_str().to_string(),
       version: VersionRange::from_str(captures_version.get(1).unwrap().as_str())?,
-      arch: Arch::from_str(captures_arch.get(2).unwrap().as_str())?,
-      os: OperatingSystem::from_str(captures_arch.get(3).unwrap().as_str())?,
-      distribution: Distribution::from_str(captures_distribution.get(1).unwrap().as_str())?,
+      arch: Arch::from_str(captures_target.get(1).unwrap().as_str())?,
+      os: OperatingSystem::from_str(captures_target.get(2).unwrap().as_str())?,
+      distribution: Distribution::from_str(captures_target.get(3).unwrap().as_str())?,
+      profile: captures_target.get(5).map(|x| x.as_str().to_string()),
     })
   }
 
@@ -78,6 +101,7 @@ impl Dependency
       arch: self.arch.clone(),
       os: self.os.clone(),
       distribution: self.distribution.clone(),
+      profile: self.profile.clone(),
     })
   }
 
@@ -94,6 +118,7 @@ impl Dependency
       && self.version.max <= other.version.max
       && self.arch == other.arch
       && self.os == other.os
+      && self.profile == other.profile
   }
 
   pub fn as_sources_dependency(&self) -> Dependency
@@ -102,6 +127,7 @@ impl Dependency
     dep.distribution = Distribution::Sources;
     dep.arch = Arch::Unknown;
     dep.os = OperatingSystem::Unknown;
+    dep.profile = None;
     dep
   }
 
@@ -112,7 +138,7 @@ impl Dependency
             self.version.to_string().bold().blue(),
             self.arch.to_string().dimmed(),
             self.os.to_string().dimmed(),
-            self.distribution.to_string().bold()
+            self.distribution_tag().bold()
     )
   }
 }
@@ -138,5 +164,23 @@ mod tests {
     assert_eq!(dep.as_ref().unwrap().clone().arch, Arch::X86_64);
     assert_eq!(dep.as_ref().unwrap().clone().os, OperatingSystem::Windows);
     assert_eq!(dep.as_ref().unwrap().clone().distribution, Distribution::Static);
+
+    let dep = Dependency::from_package_name("qt-6.5.0-aarch64-linux-shared.tar.zst");
+    assert!(dep.is_ok());
+    assert_eq!(dep.as_ref().unwrap().clone().name, "qt");
+    assert_eq!(dep.as_ref().unwrap().clone().distribution, Distribution::Shared);
+
+    let dep = Dependency::from_package_name("boost-1.84.0-x86_64-linux-static.tar.xz");
+    assert!(dep.is_ok());
+    assert_eq!(dep.as_ref().unwrap().clone().version, VersionRange::from_str("1.84.0").unwrap());
+    assert_eq!(dep.as_ref().unwrap().clone().distribution, Distribution::Static);
+    assert_eq!(dep.as_ref().unwrap().clone().profile, None);
+
+    let dep = Dependency::from_package_name("fmt-10.2.1-x86_64-linux-static-debug.tar.gz");
+    assert!(dep.is_ok());
+    assert_eq!(dep.as_ref().unwrap().clone().os, OperatingSystem::Linux);
+    assert_eq!(dep.as_ref().unwrap().clone().distribution, Distribution::Static);
+    assert_eq!(dep.as_ref().unwrap().clone().profile, Some(String::from("debug")));
+    assert_eq!(dep.as_ref().unwrap().distribution_tag(), "static-debug");
   }
 }
\ No newline at end of file
@@ -1,12 +1,17 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::resolver::Dependency;
+use crate::store::Store;
+use crate::utility::checksum::md5_file;
 
 #[derive(Debug, Clone)]
 pub struct ResolverEntry
 {
   pub dependency: Dependency,
   pub require_build: bool,
-  pub tar_path: PathBuf
+  pub tar_path: PathBuf,
+
+  /// Names of all direct and transitive dependencies, used to order builds from sources
+  pub needs: Vec<String>
 }
 
 impl ResolverEntry
@@ -17,17 +22,22 @@ impl ResolverEntry
     {
       dependency,
       require_build,
-      tar_path
+      tar_path,
+      needs: Vec::new()
     }
   }
 
-  pub fn install(&self, target_folder: &str) -> anyhow::Result<()>
+  /// Digest of package tarball, used as a key in package store and install state
+  pub fn digest(&self) -> anyhow::Result<String>
+  {
+    md5_file(self.tar_path.as_path())
+  }
+
+  /// Installs package into `target_folder` through package store. Returns installed files relative to `target_folder`
+  pub fn install(&self, store: &Store, digest: &str, target_folder: &str) -> anyhow::Result<Vec<PathBuf>>
   {
     std::fs::create_dir_all(target_folder)?;
-    crate::pack::unpack(
-      self.tar_path.as_path().to_str().unwrap(),
-      target_folder
-    )?;
-    Ok(())
+    let extracted = store.extract(self.tar_path.as_path(), digest)?;
+    store.materialize(&extracted, Path::new(target_folder))
   }
 }
\ No newline at end of file
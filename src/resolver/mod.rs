@@ -2,8 +2,10 @@ mod dependency;
 mod entry;
 mod resolver;
 mod package_getter;
+mod state;
 
 pub use dependency::Dependency;
 pub use entry::ResolverEntry;
 pub use resolver::Resolver;
-pub use package_getter::PackageGet;
\ No newline at end of file
+pub use package_getter::PackageGet;
+pub use state::InstallState;
\ No newline at end of file
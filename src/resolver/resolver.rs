@@ -1,19 +1,23 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::env::temp_dir;
-use std::path::{Path};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Duration;
 use anyhow::{anyhow, Context};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
 use crate::artifactory::Registry;
-use crate::builder::Recipe;
+use crate::builder::{BuildFingerprint, BuildProfile, Recipe};
 use crate::core;
 use crate::manifest::Manifest;
-use crate::names::{DEPENDENCIES_FOLDER};
-use crate::resolver::{Dependency, PackageGet, ResolverEntry};
-use crate::toolchains::{CMakeToolchain, ShellToolchain, Toolchain};
+use crate::names::{DEPENDENCIES_FOLDER, GENERATED_FOLDER, MERGED_FOLDER, PREFIXES_FILE};
+use crate::resolver::{Dependency, InstallState, PackageGet, ResolverEntry};
+use crate::toolchains::{AutotoolsToolchain, BuildLog, CMakeToolchain, Hooks, MakeToolchain, MesonToolchain, ShellToolchain, Toolchain};
+use crate::toolchains::utl::parallel_jobs;
 use crate::types::Distribution;
+use crate::store::Store;
+use crate::vendor::Vendor;
 
 pub struct Resolver
 {
@@ -21,23 +25,82 @@ pub struct Resolver
   pub env: Rc<core::Environment>,
   pub registry: Rc<RefCell<Registry>>,
   pub cache: Rc<crate::cache::Cache>,
-  source_only: bool
+  source_only: bool,
+  upload_built: Option<String>,
+  vendor: Option<Rc<Vendor>>,
+  store: Store,
+  merged: bool,
+  verbose: bool,
+  profile: BuildProfile,
+  jobs: usize
+}
+
+/// Package ready to be built by its toolchain in a worker thread
+struct PreparedBuild
+{
+  toolchain: Box<dyn Toolchain>,
+  recipe: Recipe,
+  directory: PathBuf,
+  distribution: Distribution,
+  log: BuildLog,
+  hooks: Hooks,
+  fingerprint: BuildFingerprint
 }
 
 impl Resolver
 {
-  pub fn new(config: Rc<core::Config>, env: Rc<core::Environment>, registry: Rc<RefCell<Registry>>, cache: Rc<crate::cache::Cache>, source_only: bool) -> Self
+  pub fn new(config: Rc<core::Config>, env: Rc<core::Environment>, registry: Rc<RefCell<Registry>>, cache: Rc<crate::cache::Cache>, source_only: bool, upload_built: Option<String>) -> Self
   {
     Self
     {
+      store: Store::new(&config),
       config,
       env,
       registry,
       cache,
-      source_only
+      source_only,
+      upload_built,
+      vendor: None,
+      merged: false,
+      verbose: false,
+      profile: BuildProfile::default(),
+      jobs: 1
     }
   }
 
+  pub fn with_vendor(mut self, vendor: Option<Rc<Vendor>>) -> Self
+  {
+    self.vendor = vendor;
+    self
+  }
+
+  pub fn with_merged_view(mut self, merged: bool) -> Self
+  {
+    self.merged = merged;
+    self
+  }
+
+  /// Build profile for packages built from sources. Also selects matching binary packages
+  pub fn with_profile(mut self, profile: BuildProfile) -> Self
+  {
+    self.profile = profile;
+    self
+  }
+
+  /// Stream build output of packages built from sources to terminal
+  pub fn with_verbose(mut self, verbose: bool) -> Self
+  {
+    self.verbose = verbose;
+    self
+  }
+
+  /// Maximum number of packages built from sources concurrently
+  pub fn with_jobs(mut self, jobs: usize) -> Self
+  {
+    self.jobs = jobs.max(1);
+    self
+  }
+
   pub fn resolve(&self, path: &str) -> anyhow::Result<()>
   {
     let manifest = Manifest::from_directory(path)?;
@@ -54,11 +117,7 @@ impl Resolver
         acc
       });
 
-    for x in &mut tree {
-      if x.require_build {
-        self.build(x)?;
-      }
-    }
+    self.build_all(&mut tree)?;
 
     let pb = ProgressBar::new(tree.len() as u64);
     pb.set_message("installing dependencies");
@@ -69,13 +128,40 @@ impl Resolver
     );
     let install_path = Path::new(path)
       .join(DEPENDENCIES_FOLDER);
+    let generated_path = install_path.join(GENERATED_FOLDER);
+    let merged_path = generated_path.join(MERGED_FOLDER);
+    if merged_path.exists() {
+      std::fs::remove_dir_all(&merged_path)?;
+    }
+    let mut state = InstallState::load(&install_path);
+    for x in state.remove_dropped(&install_path, &tree, self.profile.tag().as_deref())? {
+      println!("🗑️ removed {}", x.bold());
+    }
+
+    let mut prefixes = Vec::new();
     for x in &tree {
       pb.set_message(format!("installing {}", x.dependency.pretty_print()));
-      x.install(install_path.to_str().context("failed to convert path to string")?)?;
+      let digest = x.digest()?;
+      let (prefix, installed) = state.install(&install_path, x, &digest, &self.store)?;
+      if self.merged {
+        x.install(&self.store, &digest, merged_path.to_str().context("failed to convert path to string")?)?;
+      }
+      prefixes.push(prefix.canonicalize()?);
       pb.inc(1);
       print!("\x1b[A\x1b[2K\r");
-      println!("✅ installed {}\n", x.dependency.pretty_print());
+      match installed {
+        true => println!("✅ installed {}\n", x.dependency.pretty_print()),
+        false => println!("✔️ {} is up to date\n", x.dependency.pretty_print())
+      }
     }
+    std::fs::create_dir_all(&generated_path)?;
+    std::fs::write(
+      generated_path.join(PREFIXES_FILE),
+      prefixes
+        .iter()
+        .map(|x| format!("{}\n", x.display()))
+        .collect::<String>()
+    )?;
     println!("\x1b[A\x1b[2K\r");
     pb.finish_with_message(format!("installed {} dependencies for {}",
       tree.len().to_string().magenta().bold(),
@@ -98,11 +184,18 @@ impl Resolver
         self.env.arch,
         self.env.os,
         x.1.distribution
-      );
+      ).with_profile(self.profile.tag());
 
-      let entry = self.try_get(&dependency)?;
-      let folded_manifest = Manifest::from_tar_gz(entry.tar_path.to_str().context("failed to convert path to string")?)?;
+      let mut entry = self.try_get(&dependency)?;
+      let folded_manifest = Manifest::from_tarball(entry.tar_path.to_str().context("failed to convert path to string")?)?;
       let sub_deps = self.collect_recursively(folded_manifest)?;
+      // package prepared for build resolves its whole subtree, so it waits for every package below it
+      entry.needs = sub_deps
+        .iter()
+        .map(|x| x.dependency.name.clone())
+        .collect();
+      entry.needs.sort();
+      entry.needs.dedup();
       deps.extend(sub_deps);
       deps.push(entry);
     }
@@ -112,6 +205,16 @@ impl Resolver
 
   pub fn try_get(&self, dependency: &Dependency) -> anyhow::Result<ResolverEntry>
   {
+    if let Some(vendor) = &self.vendor {
+      if !self.source_only {
+        if let Ok(x) = vendor.get(dependency, false) {
+          return Ok(ResolverEntry::new(dependency.with_updated_version_from_archive_name(x.as_path())?, false, x));
+        }
+      }
+      if let Ok(x) = vendor.get(dependency, true) {
+        return Ok(ResolverEntry::new(dependency.with_updated_version_from_archive_name(x.as_path())?, true, x));
+      }
+    }
     if self.source_only && !dependency.name.starts_with("cmake") {
       match self.cache.get(&dependency, false) {
         Ok(x) => Ok(ResolverEntry::new(dependency.with_updated_version_from_archive_name(x.as_path())?, false, x)),
@@ -140,21 +243,109 @@ impl Resolver
     }
   }
 
-  pub fn build(&self, entry: &mut ResolverEntry) -> anyhow::Result<ResolverEntry>
+  pub fn try_get_sources(&self, dependency: &Dependency) -> anyhow::Result<std::path::PathBuf>
+  {
+    match self.cache.get(&dependency.as_sources_dependency(), false) {
+      Ok(x) => Ok(x),
+      Err(_) => self.registry.borrow().get(dependency, true)
+    }
+  }
+
+  /// Builds entries requiring build in dependency order. Up to `jobs` toolchains run concurrently in worker
+  /// threads, each limited to its share of available cores. Everything else happens on this thread
+  fn build_all(&self, tree: &mut [ResolverEntry]) -> anyhow::Result<()>
   {
+    let mut pending = (0..tree.len())
+      .filter(|x| tree[*x].require_build)
+      .collect::<Vec<_>>();
+    if pending.is_empty() {
+      return Ok(());
+    }
     let pb = ProgressBar::new_spinner()
       .with_finish(ProgressFinish::AndClear);
     pb.enable_steady_tick(Duration::from_millis(100));
-    pb.set_message(format!("building {}", entry.dependency.pretty_print()));
+
+    let (sender, receiver) = std::sync::mpsc::channel::<(usize, anyhow::Result<PathBuf>)>();
+    let mut running: HashMap<usize, BuildFingerprint> = HashMap::new();
+    let mut failure: Option<anyhow::Error> = None;
+    while !pending.is_empty() || !running.is_empty() {
+      while failure.is_none() && running.len() < self.jobs {
+        let building = pending
+          .iter()
+          .chain(running.keys())
+          .copied()
+          .collect::<Vec<_>>();
+        let Some(position) = next_ready(tree, &pending, &building) else { break };
+        let index = pending.remove(position);
+        let prepared = match self.prepare(&mut tree[index]) {
+          Ok(Some(x)) => x,
+          Ok(None) => continue,
+          Err(e) => {
+            failure = Some(e);
+            break;
+          }
+        };
+
+        let cores = (parallel_jobs() / self.jobs.min(pending.len() + running.len() + 1)).max(1);
+        let name = tree[index].dependency.pretty_print();
+        let sender = sender.clone();
+        running.insert(index, prepared.fingerprint.clone());
+        std::thread::spawn(move || {
+          let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| prepared.toolchain
+            .build_from_recipe(
+              &prepared.recipe,
+              prepared.directory.to_str().context("failed to convert path to string")?,
+              prepared.distribution,
+              &prepared.log,
+              &prepared.hooks,
+              cores
+            )
+          ))
+            .unwrap_or_else(|_| Err(anyhow!("build thread panicked")))
+            .context(format!("failed to build {}", name));
+          let _ = sender.send((index, result));
+        });
+      }
+
+      if running.is_empty() {
+        return Err(failure.unwrap_or(anyhow!("circular dependency between {}", pending
+          .iter()
+          .map(|x| tree[*x].dependency.name.clone())
+          .collect::<Vec<_>>()
+          .join(", ")
+        )));
+      }
+      pb.set_message(format!("building {}", running
+        .keys()
+        .map(|x| tree[*x].dependency.pretty_print())
+        .collect::<Vec<_>>()
+        .join(", ")
+      ));
+
+      let (index, result) = receiver.recv()?;
+      let fingerprint = running
+        .remove(&index)
+        .context("received result of unknown build")?;
+      match result.and_then(|x| self.finish(&mut tree[index], &fingerprint, &x)) {
+        Ok(()) => pb.println(format!("🔨 built {}", tree[index].dependency.pretty_print())),
+        Err(e) => { failure.get_or_insert(e); }
+      }
+    }
+    pb.finish_and_clear();
+    failure.map_or(Ok(()), Err)
+  }
+
+  /// Unpacks package, fetches its sources and installs its dependencies. Returns `None` if the package
+  /// was already built with the same fingerprint
+  fn prepare(&self, entry: &mut ResolverEntry) -> anyhow::Result<Option<PreparedBuild>>
+  {
     let build_directory = temp_dir()
       .join(entry.dependency.name.clone())
-      .join(entry.dependency.version.to_string().clone());
-    std::fs::create_dir_all(&build_directory)?;
-    crate::pack::unpack(entry.tar_path.to_str().unwrap(), build_directory.to_str().unwrap())?;
-    let _manifest = Manifest::from_directory(build_directory.to_str().unwrap())?;
-    let recipe = Recipe::from_directory(build_directory.to_str().unwrap())?;
-
-    self.resolve(build_directory.to_str().unwrap())?;
+      .join(entry.dependency.version.to_string().clone())
+      .join(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos().to_string());
+    // fingerprint hashes exactly the recipe that gets built
+    let recipe_data = Recipe::read_from_tarball(entry.tar_path.to_str().unwrap())?;
+    let recipe = Recipe::from_slice(&recipe_data)?;
 
     let recipe_toolchain = match entry.dependency.distribution {
       Distribution::Static => recipe.static_toolchain.clone().context(format!("recipe for {} does not have a static or shared toolchain", entry.dependency))?,
@@ -162,25 +353,135 @@ impl Resolver
       _ => { return Err(anyhow!("unsupported distribution for build: {} (package {})", entry.dependency.distribution, entry.dependency)); }
     };
 
-    let export_dir = if recipe_toolchain.toolchain.cmake.is_some() {
-      CMakeToolchain::new(&self.config)
-        .build_from_recipe(&recipe, build_directory.to_str().unwrap(), entry.dependency.distribution.clone())?
+    let toolchain: Box<dyn Toolchain> = if recipe_toolchain.toolchain.cmake.is_some() {
+      Box::new(CMakeToolchain::new(&self.config, &self.env, &self.profile))
+    } else if recipe_toolchain.toolchain.meson.is_some() {
+      Box::new(MesonToolchain::new(&self.env, &self.profile))
+    } else if recipe_toolchain.toolchain.autotools.is_some() {
+      Box::new(AutotoolsToolchain::new(&self.env, &self.profile))
+    } else if recipe_toolchain.toolchain.make.is_some() {
+      Box::new(MakeToolchain::new(&self.env, &self.profile))
     } else if recipe_toolchain.toolchain.shell.is_some() {
-      ShellToolchain::new()
-        .build_from_recipe(&recipe, build_directory.to_str().unwrap(), entry.dependency.distribution.clone())?
+      Box::new(ShellToolchain::new(&self.env, &self.profile))
     } else {
       return Err(anyhow!("unsupported toolchain for build: {:?}", recipe_toolchain.toolchain));
     };
 
+    let fingerprint = BuildFingerprint::new(
+      toolchain.configuration(),
+      toolchain.build_type(),
+      &recipe_data
+    );
+    if let Ok(x) = self.cache.get_built(&entry.dependency, &fingerprint) {
+      entry.tar_path = x;
+      return Ok(None);
+    }
+
+    std::fs::create_dir_all(&build_directory)?;
+    crate::pack::unpack(entry.tar_path.to_str().unwrap(), build_directory.to_str().unwrap())?;
+
+    let log = BuildLog::new(&build_directory, self.verbose)?;
+    recipe
+      .prepare_sources(&build_directory, &self.cache, &log)
+      .context(format!("failed to prepare sources of {}", entry.dependency.pretty_print()))?;
+
+    self.resolve(build_directory.to_str().unwrap())?;
+
+    let hooks = Hooks::new(&recipe, build_directory.to_str().unwrap(), &self.env, &self.profile, entry.dependency.distribution)?;
+    Ok(Some(PreparedBuild
+    {
+      toolchain,
+      recipe,
+      directory: build_directory,
+      distribution: entry.dependency.distribution,
+      log,
+      hooks,
+      fingerprint
+    }))
+  }
+
+  /// Packs built package into cache and uploads it if requested
+  fn finish(&self, entry: &mut ResolverEntry, fingerprint: &BuildFingerprint, export_dir: &Path) -> anyhow::Result<()>
+  {
+    fingerprint.save(export_dir.to_str().unwrap())?;
+
     let tarball = crate::pack::pack_for_cache(
       export_dir.to_str().unwrap(),
-      entry.dependency.arch.clone(),
-      entry.dependency.distribution.clone(),
-      entry.dependency.os.clone()
+      entry.dependency.arch,
+      entry.dependency.distribution,
+      entry.dependency.os,
+      entry.dependency.profile.as_deref()
     )?;
-    self.cache.put(tarball.as_str())?;
-    entry.tar_path = self.cache.get(&entry.dependency, false)?;
+    let cached = self.cache.put_built(tarball.as_str(), fingerprint);
+    match (&cached, &self.upload_built) {
+      // tarball is removed by push
+      (Ok(_), Some(registry_name)) => {
+        let _ = self
+          .upload(registry_name, tarball.as_str(), &entry.dependency)
+          .map_err(|e| eprintln!("{}: failed to upload {} to {} ({})",
+            "warning".yellow().bold(),
+            entry.dependency.pretty_print(),
+            registry_name.bold().cyan(),
+            e.to_string().yellow()
+          ));
+      },
+      _ => { let _ = std::fs::remove_file(&tarball); }
+    }
+    entry.tar_path = cached?;
+    Ok(())
+  }
+
+  fn upload(&self, registry_name: &str, tarball: &str, dependency: &Dependency) -> anyhow::Result<()>
+  {
+    let registry = self.registry.borrow();
+    let remote = registry
+      .remotes
+      .iter()
+      .find(|x| x.name == registry_name)
+      .context(format!("registry {} not found", registry_name))?;
+    remote.push(tarball, dependency, false)?;
+    Ok(())
+  }
+}
 
-    Ok(ResolverEntry::new(entry.dependency.clone(), true, build_directory))
+/// Position in `pending` of the first entry whose dependencies are not being built anymore.
+/// Dependencies built from sources must be in cache before dependent package is prepared
+fn next_ready(tree: &[ResolverEntry], pending: &[usize], building: &[usize]) -> Option<usize>
+{
+  pending
+    .iter()
+    .position(|x| tree[*x].needs
+      .iter()
+      .all(|y| !building.iter().any(|z| tree[*z].dependency.name == *y))
+    )
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_builds_wait_for_dependencies() {
+    let entry = |name: &str, needs: &[&str]| {
+      let mut x = ResolverEntry::new(
+        Dependency::from_package_name(format!("{}-1.0.0-x86_64-linux-static.tar.gz", name).as_str()).unwrap(),
+        true,
+        PathBuf::new()
+      );
+      x.needs = needs.iter().map(|y| y.to_string()).collect();
+      x
+    };
+    let tree = [entry("zlib", &[]), entry("png", &["zlib"]), entry("app", &["png", "fmt"]), entry("fmt", &[])];
+
+    assert_eq!(next_ready(&tree, &[1, 2, 3], &[0, 1, 2, 3]), Some(2));
+    assert_eq!(next_ready(&tree, &[1, 2], &[0, 1, 2]), None);
+    assert_eq!(next_ready(&tree, &[1, 2], &[1, 2]), Some(0));
+    assert_eq!(next_ready(&tree, &[2], &[2, 3]), None);
+    assert_eq!(next_ready(&tree, &[2], &[2]), Some(0));
+
+    // binary png in between does not let app start while zlib is built from sources
+    let tree = [entry("zlib", &[]), entry("app", &["png", "zlib"])];
+    assert_eq!(next_ready(&tree, &[1], &[0, 1]), None);
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::names::INSTALL_STATE_FILE;
+use anyhow::Context;
+use crate::resolver::{Dependency, ResolverEntry};
+use crate::store::Store;
+use crate::types::{Arch, Distribution, OperatingSystem, VersionRange};
+
+/// Packages installed into `dependencies` folder, stored in `.puff-state.json`. Keyed by prefix folder name
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct InstallState
+{
+  pub packages: BTreeMap<String, InstalledPackage>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage
+{
+  pub version: VersionRange,
+  pub arch: Arch,
+  pub os: OperatingSystem,
+  pub distribution: Distribution,
+
+  /// Build profile tag, `None` for release
+  #[serde(default)]
+  pub profile: Option<String>,
+  pub digest: String,
+  pub files: Vec<PathBuf>
+}
+
+impl InstalledPackage
+{
+  pub fn new(dependency: &Dependency, digest: String, files: Vec<PathBuf>) -> Self
+  {
+    Self
+    {
+      version: dependency.version,
+      arch: dependency.arch,
+      os: dependency.os,
+      distribution: dependency.distribution,
+      profile: dependency.profile.clone(),
+      digest,
+      files
+    }
+  }
+
+  /// Removes installed files from `prefix`, then prunes directories left empty
+  pub fn uninstall(&self, prefix: &Path) -> anyhow::Result<()>
+  {
+    for x in &self.files {
+      let path = prefix.join(x);
+      match std::fs::remove_file(&path) {
+        Ok(_) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+        Err(e) => return Err(anyhow::anyhow!("failed to remove {} ({})", path.display(), e))
+      }
+      let mut parent = path.parent();
+      while let Some(directory) = parent {
+        if !directory.starts_with(prefix) || std::fs::remove_dir(directory).is_err() {
+          break;
+        }
+        parent = directory.parent();
+      }
+    }
+    Ok(())
+  }
+}
+
+impl InstallState
+{
+  /// Loads state from `install_path`. Missing or unreadable state is treated as nothing installed
+  pub fn load(install_path: &Path) -> Self
+  {
+    std::fs::read_to_string(install_path.join(INSTALL_STATE_FILE))
+      .ok()
+      .and_then(|x| serde_json::from_str(&x).ok())
+      .unwrap_or_default()
+  }
+
+  pub fn save(&self, install_path: &Path) -> anyhow::Result<()>
+  {
+    std::fs::create_dir_all(install_path)?;
+    std::fs::write(install_path.join(INSTALL_STATE_FILE), serde_json::to_string_pretty(self)?)?;
+    Ok(())
+  }
+
+  /// Checks whether package with the same identity and contents is already installed into `install_path`
+  pub fn is_installed(&self, install_path: &Path, dependency: &Dependency, digest: &str) -> bool
+  {
+    self.packages
+      .get(&dependency.prefix_name())
+      .is_some_and(|x| x.digest == digest
+        && x.arch == dependency.arch
+        && x.os == dependency.os
+        && x.distribution == dependency.distribution
+        && x.profile == dependency.profile
+        && install_path.join(dependency.prefix_name()).exists()
+      )
+  }
+
+  /// Installs package into its own prefix in `install_path`, replacing previously installed version.
+  /// Returns the prefix and whether anything was installed
+  pub fn install(&mut self, install_path: &Path, entry: &ResolverEntry, digest: &str, store: &Store) -> anyhow::Result<(PathBuf, bool)>
+  {
+    let name = entry.dependency.prefix_name();
+    let prefix = install_path.join(&name);
+    if self.is_installed(install_path, &entry.dependency, digest) {
+      return Ok((prefix, false));
+    }
+    if let Some(previous) = self.packages.remove(&name) {
+      previous.uninstall(&prefix)?;
+    }
+    let files = entry.install(store, digest, prefix.to_str().context("failed to convert path to string")?)?;
+    self.packages.insert(name, InstalledPackage::new(&entry.dependency, digest.to_string(), files));
+    self.save(install_path)?;
+    Ok((prefix, true))
+  }
+
+  /// Uninstalls packages of `profile` that are no longer in `tree`. Packages of other profiles are kept.
+  /// Returns prefix names of removed packages
+  pub fn remove_dropped(&mut self, install_path: &Path, tree: &[ResolverEntry], profile: Option<&str>) -> anyhow::Result<Vec<String>>
+  {
+    let removed = self.packages
+      .iter()
+      .filter(|x| x.1.profile.as_deref() == profile)
+      .filter(|x| !tree.iter().any(|y| y.dependency.prefix_name() == *x.0))
+      .map(|x| x.0.clone())
+      .collect::<Vec<_>>();
+    for x in &removed {
+      if let Some(package) = self.packages.remove(x) {
+        package.uninstall(&install_path.join(x))?;
+        self.save(install_path)?;
+      }
+    }
+    Ok(removed)
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use std::str::FromStr;
+  use super::*;
+
+  #[test]
+  fn test_uninstall() {
+    let dir = tempfile::tempdir().unwrap();
+    let prefix = dir.path().join("fmt");
+    std::fs::create_dir_all(prefix.join("include/fmt")).unwrap();
+    std::fs::create_dir_all(prefix.join("lib")).unwrap();
+    std::fs::write(prefix.join("include/fmt/core.h"), b"").unwrap();
+    std::fs::write(prefix.join("lib/libfmt.a"), b"").unwrap();
+    std::fs::write(prefix.join("lib/user.txt"), b"").unwrap();
+
+    let package = InstalledPackage {
+      version: VersionRange::from_str("1.0.0").unwrap(),
+      arch: Arch::X86_64,
+      os: OperatingSystem::Linux,
+      distribution: Distribution::Static,
+      profile: None,
+      digest: String::from("d41d8cd98f00b204e9800998ecf8427e"),
+      files: vec![PathBuf::from("include/fmt/core.h"), PathBuf::from("lib/libfmt.a")]
+    };
+    package.uninstall(&prefix).unwrap();
+    assert!(!prefix.join("include").exists());
+    assert!(prefix.join("lib/user.txt").exists());
+    assert!(!prefix.join("lib/libfmt.a").exists());
+  }
+
+  #[test]
+  fn test_profiles_coexist() {
+    let dir = tempfile::tempdir().unwrap();
+    let package = dir.path().join("package");
+    std::fs::create_dir_all(package.join("lib")).unwrap();
+    std::fs::write(package.join("lib/libfmt.a"), b"").unwrap();
+    let entry = |name: &str| {
+      let tarball = dir.path().join(name);
+      crate::pack::pack(package.to_str().unwrap(), tarball.to_str().unwrap(), crate::types::Compression::Gzip).unwrap();
+      ResolverEntry::new(Dependency::from_std_path(&tarball).unwrap(), false, tarball)
+    };
+    let release = entry("fmt-1.0.0-x86_64-linux-static.tar.gz");
+    let debug = entry("fmt-1.0.0-x86_64-linux-static-debug.tar.gz");
+    let store = Store { path: dir.path().join("store"), link_mode: crate::core::LinkMode::Copy };
+    let install_path = dir.path().join("dependencies");
+
+    let mut state = InstallState::default();
+    for x in [&release, &debug] {
+      let (prefix, installed) = state.install(&install_path, x, &x.digest().unwrap(), &store).unwrap();
+      assert!(installed);
+      assert!(prefix.join("lib/libfmt.a").exists());
+    }
+    assert!(install_path.join("fmt/lib/libfmt.a").exists());
+    assert!(install_path.join("fmt-debug/lib/libfmt.a").exists());
+
+    let mut state = InstallState::load(&install_path);
+    assert!(!state.install(&install_path, &release, &release.digest().unwrap(), &store).unwrap().1);
+    assert!(state.remove_dropped(&install_path, std::slice::from_ref(&release), None).unwrap().is_empty());
+    assert_eq!(state.remove_dropped(&install_path, &[], Some("debug")).unwrap(), vec![String::from("fmt-debug")]);
+    assert!(!install_path.join("fmt-debug/lib").exists());
+    assert!(install_path.join("fmt/lib/libfmt.a").exists());
+  }
+}
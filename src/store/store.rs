@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use crate::core;
+use crate::core::LinkMode;
+
+/// Global storage of unpacked packages, keyed by tarball digest. Installs are materialized from here
+pub struct Store
+{
+  pub path: PathBuf,
+  pub link_mode: LinkMode
+}
+
+impl Store
+{
+  pub fn new(config: &core::Config) -> Self
+  {
+    Self
+    {
+      path: config.directories.dirs.data_dir().join("store"),
+      link_mode: config.install.link_mode
+    }
+  }
+
+  /// Unpacks tarball with given digest into store if it is not there yet. Returns path to unpacked package
+  pub fn extract(&self, tarball: &Path, digest: &str) -> anyhow::Result<PathBuf>
+  {
+    let target = self.path.join(digest);
+    if target.exists() {
+      return Ok(target);
+    }
+
+    std::fs::create_dir_all(&self.path)?;
+    let temporary = tempfile::Builder::new()
+      .prefix(format!(".{}-", digest).as_str())
+      .tempdir_in(&self.path)?;
+    crate::pack::unpack(
+      tarball.to_str().context("failed to convert path to string")?,
+      temporary.path().to_str().context("failed to convert path to string")?
+    )?;
+    match std::fs::rename(temporary.keep(), &target) {
+      Ok(_) => Ok(target),
+      Err(_) if target.exists() => Ok(target),
+      Err(e) => Err(e.into())
+    }
+  }
+
+  /// Links or copies every file of unpacked package into `target`. Returns materialized files relative to `target`
+  pub fn materialize(&self, source: &Path, target: &Path) -> anyhow::Result<Vec<PathBuf>>
+  {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(source).sort_by_file_name() {
+      let entry = entry?;
+      let relative = entry.path().strip_prefix(source)?;
+      let destination = target.join(relative);
+      if entry.file_type().is_dir() {
+        std::fs::create_dir_all(&destination)?;
+        continue;
+      }
+      if destination.symlink_metadata().is_ok() {
+        std::fs::remove_file(&destination)?;
+      }
+      if entry.file_type().is_symlink() {
+        symlink(&std::fs::read_link(entry.path())?, &destination)?;
+      } else {
+        self.link(entry.path(), &destination)?;
+      }
+      files.push(relative.to_path_buf());
+    }
+    Ok(files)
+  }
+
+  fn link(&self, source: &Path, destination: &Path) -> anyhow::Result<()>
+  {
+    let linked = match self.link_mode {
+      LinkMode::Hardlink => std::fs::hard_link(source, destination).is_ok(),
+      LinkMode::Symlink => symlink(source, destination).is_ok(),
+      LinkMode::Copy => false
+    };
+    // hardlinks do not work across filesystems, so copying is always a fallback
+    if !linked {
+      std::fs::copy(source, destination)
+        .context(format!("failed to copy {} to {}", source.display(), destination.display()))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, link: &Path) -> std::io::Result<()> { std::os::unix::fs::symlink(original, link) }
+
+#[cfg(windows)]
+fn symlink(original: &Path, link: &Path) -> std::io::Result<()> { std::os::windows::fs::symlink_file(original, link) }
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[cfg(unix)]
+  #[test]
+  fn test_materialize() {
+    use std::os::unix::fs::MetadataExt;
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("store/abc");
+    std::fs::create_dir_all(source.join("lib")).unwrap();
+    std::fs::write(source.join("lib/liba.so.1"), b"elf").unwrap();
+    symlink(Path::new("liba.so.1"), &source.join("lib/liba.so")).unwrap();
+
+    let store = Store { path: dir.path().join("store"), link_mode: LinkMode::Hardlink };
+    let target = dir.path().join("dependencies");
+    let files = store.materialize(&source, &target).unwrap();
+    assert_eq!(files, vec![PathBuf::from("lib/liba.so"), PathBuf::from("lib/liba.so.1")]);
+    assert_eq!(std::fs::metadata(target.join("lib/liba.so.1")).unwrap().nlink(), 2);
+    assert_eq!(std::fs::read_link(target.join("lib/liba.so")).unwrap(), PathBuf::from("liba.so.1"));
+  }
+}
@@ -0,0 +1,97 @@
+use std::env::temp_dir;
+use std::path::PathBuf;
+use anyhow::Context;
+use crate::builder::{BuildProfile, Recipe};
+use crate::core::TargetConfig;
+use crate::toolchains::{BuildLog, HookStage, Hooks, Toolchain};
+use crate::toolchains::utl::{apply_target, dependency_environment, target_configuration};
+use crate::types::Distribution;
+
+pub struct AutotoolsToolchain
+{
+  /// Target triple for `--host`, only set when cross-compiling
+  pub host: Option<String>,
+  pub profile: BuildProfile,
+  pub target: Option<TargetConfig>,
+}
+
+impl AutotoolsToolchain
+{
+  pub fn new(env: &crate::core::Environment, profile: &BuildProfile) -> Self
+  {
+    Self
+    {
+      host: env.is_cross().then(|| env.host_triple()),
+      profile: profile.clone(),
+      target: env.target.clone()
+    }
+  }
+}
+
+impl Toolchain for AutotoolsToolchain
+{
+  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution, log: &BuildLog, hooks: &Hooks, jobs: usize) -> anyhow::Result<PathBuf>
+  {
+    let toolchain = recipe
+      .extract_toolchain(distribution)?
+      .autotools
+      .context("autotools toolchain was requested to build package but recipe is not configured for autotools")?;
+    let export_folder = temp_dir()
+      .join(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos().to_string())
+      .join(crate::names::TARGET_FOLDER)
+      .join(crate::names::EXPORT_FOLDER);
+    let mut environment = dependency_environment(source_directory, &self.profile)?;
+    apply_target(&mut environment, self.target.as_ref());
+    environment.extend(toolchain.env.clone().unwrap_or_default());
+    environment.extend(hooks.environment.clone());
+    let command = |program: &str| {
+      let mut command = std::process::Command::new(program);
+      command
+        .current_dir(source_directory)
+        .envs(environment.iter().map(|x| (&x.0, &x.1)));
+      command
+    };
+
+    hooks.run(HookStage::PreConfigure, &export_folder, log)?;
+    if toolchain.autoreconf {
+      log.run(command("autoreconf").arg("-fi"), "autoreconf")?;
+    }
+
+    let mut configure = command("sh");
+    configure
+      .arg("./configure")
+      .arg(format!("--prefix={}", export_folder.display()))
+      .args(match distribution {
+        Distribution::Shared => ["--enable-shared", "--disable-static"],
+        _ => ["--enable-static", "--disable-shared"]
+      });
+    if let Some(host) = &self.host {
+      configure.arg(format!("--host={}", host));
+    }
+    configure.args(toolchain.configure_args.clone().unwrap_or_default());
+    log.run(&mut configure, "configure")?;
+
+    log.run(command("make")
+      .arg(format!("-j{}", jobs))
+      .args(toolchain.make_targets.clone().unwrap_or_default()), "make")?;
+    hooks.run(HookStage::PostBuild, &export_folder, log)?;
+    log.run(command("make").arg("install"), "make install")?;
+    hooks.run(HookStage::PostInstall, &export_folder, log)?;
+
+    crate::toolchains::utl::copy_package_metafiles(
+      source_directory,
+      export_folder
+        .to_str()
+        .context("failed to convert export directory path to string")?
+    )?;
+
+    Ok(export_folder)
+  }
+
+  fn configuration(&self) -> String
+  {
+    format!("autotools: {}{}", self.host.as_deref().unwrap_or("native"), target_configuration(self.target.as_ref()))
+  }
+
+  fn build_type(&self) -> String { self.profile.configuration() }
+}
@@ -1,8 +1,18 @@
 use std::path::PathBuf;
 use crate::builder::Recipe;
+use crate::toolchains::{BuildLog, Hooks};
 use crate::types::Distribution;
 
-pub trait Toolchain
+/// Toolchains are created on the main thread and moved into a worker thread for the build
+pub trait Toolchain: Send
 {
-  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution) -> anyhow::Result<PathBuf>;
-}
\ No newline at end of file
+  /// Builds package unpacked into `source_directory`, writing output of every step into `log` and running
+  /// recipe `hooks` around configure, build and install steps. Build tools are limited to `jobs` parallel
+  /// processes. Returns export folder
+  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution, log: &BuildLog, hooks: &Hooks, jobs: usize) -> anyhow::Result<PathBuf>;
+
+  /// Describes everything in toolchain configuration that affects produced binaries
+  fn configuration(&self) -> String;
+
+  fn build_type(&self) -> String;
+}
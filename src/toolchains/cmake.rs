@@ -1,34 +1,90 @@
+use std::collections::{BTreeMap, HashMap};
 use std::env::temp_dir;
 use std::path::PathBuf;
-use anyhow::{Context, ensure};
-use crate::builder::Recipe;
-use crate::toolchains::Toolchain;
-use crate::types::Distribution;
+use anyhow::Context;
+use crate::builder::{BuildProfile, Recipe};
+use crate::toolchains::{BuildLog, HookStage, Hooks, Toolchain};
+use crate::names::DEPENDENCIES_FOLDER;
+use crate::types::{Arch, CMakeDefinition, CMakeType, Distribution, OperatingSystem};
 
 pub struct CMakeToolchain
 {
   pub configure_command: String,
-  pub configure_additional_arguments: Vec<String>,
+  pub definitions: BTreeMap<String, CMakeDefinition>,
+  pub build_type: String,
+  pub os: OperatingSystem,
+  pub arch: Arch,
+  pub profile: BuildProfile,
+
+  /// Environment variables of target profile, applied to every step
+  pub environment: Vec<(String, String)>,
 }
 
 impl CMakeToolchain
 {
-  pub fn new(config: &crate::core::Config) -> Self
+  /// Uses selected target profile from `env` if any, otherwise global cmake settings from config
+  pub fn new(config: &crate::core::Config, env: &crate::core::Environment, profile: &BuildProfile) -> Self
   {
+    let (configure_command, definitions, environment) = match &env.target {
+      Some(target) => (
+        target.cmake_command.clone().unwrap_or(String::from("cmake")),
+        [
+          ("CMAKE_TOOLCHAIN_FILE", CMakeType::Filepath, &target.toolchain_file),
+          ("CMAKE_SYSROOT", CMakeType::Path, &target.sysroot),
+          ("CMAKE_C_COMPILER_LAUNCHER", CMakeType::String, &target.compiler_launcher),
+          ("CMAKE_CXX_COMPILER_LAUNCHER", CMakeType::String, &target.compiler_launcher)
+        ]
+          .into_iter()
+          .filter_map(|(name, kind, value)| value.as_ref().map(|x| (
+            name.to_string(),
+            CMakeDefinition::Typed { kind, value: x.clone() }
+          )))
+          .collect(),
+        target.env
+          .iter()
+          .map(|x| (x.0.clone(), x.1.clone()))
+          .collect::<Vec<_>>()
+      ),
+      None => (
+        config.toolchain.cmake.configure_command.clone(),
+        config.toolchain.cmake.configure_additional_definitions
+          .clone()
+          .into_iter()
+          .collect(),
+        Vec::new()
+      )
+    };
     Self
     {
-      configure_command: config.toolchain.cmake.configure_command.clone(),
-      configure_additional_arguments: config.toolchain.cmake.configure_additional_definitions
-        .iter()
-        .map(|x| format!("{}={}", x.0, x.1))
-        .collect()
+      configure_command,
+      definitions,
+      build_type: profile.build_type.clone(),
+      os: env.os,
+      arch: env.arch,
+      profile: profile.clone(),
+      environment
     }
   }
 }
 
+impl CMakeToolchain
+{
+  /// Values of `{prefix}`, `{arch}`, `{os}` and `{deps}` placeholders in definitions
+  fn placeholders(&self, source_directory: &str, prefix: &std::path::Path) -> HashMap<&'static str, String>
+  {
+    let path = |x: &std::path::Path| x.to_string_lossy().replace('\\', "/");
+    HashMap::from([
+      ("prefix", path(prefix)),
+      ("arch", self.arch.to_string()),
+      ("os", self.os.to_string()),
+      ("deps", path(&std::path::Path::new(source_directory).join(DEPENDENCIES_FOLDER)))
+    ])
+  }
+}
+
 impl Toolchain for CMakeToolchain
 {
-  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution) -> anyhow::Result<PathBuf>
+  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution, log: &BuildLog, hooks: &Hooks, jobs: usize) -> anyhow::Result<PathBuf>
   {
     let target_temp = temp_dir()
       .join(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos().to_string())
@@ -39,51 +95,65 @@ impl Toolchain for CMakeToolchain
       .arg(source_directory)
       .arg("-B")
       .arg(target_temp.clone());
-    for x in &self.configure_additional_arguments {
-      command.arg("-D").arg(x);
+    let export_folder = target_temp
+      .clone()
+      .join(crate::names::EXPORT_FOLDER);
+    let variables = self.placeholders(source_directory, &export_folder);
+    for x in &self.definitions {
+      command.arg(x.1.to_argument(x.0, &variables));
+    }
+    let prefixes = crate::toolchains::utl::dependency_prefixes(source_directory)?;
+    if !prefixes.is_empty() {
+      command.arg(format!("-DCMAKE_PREFIX_PATH={}", prefixes
+        .iter()
+        .map(|x| x.to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>()
+        .join(";")
+      ));
     }
-    command.arg(format!("-DCMAKE_PREFIX_PATH={}", crate::names::DEPENDENCIES_FOLDER));
-    command.arg(format!("-DCMAKE_BUILD_TYPE={}", "Release"));
+    command.arg(format!("-DCMAKE_BUILD_TYPE={}", self.build_type));
+    // initial values of CMAKE_<LANG>_FLAGS are taken from environment
+    command.envs(self.profile.environment());
+    command.envs(self.environment.clone());
+    command.envs(hooks.environment.clone());
 
     let toolchain = recipe
       .extract_toolchain(distribution)?
       .cmake
       .context("cmake toolchain was requested to build package but recipe is not configured for cmake")?;
     // generator
-    if let Some(generator) = toolchain.generator {
+    if let Some(generator) = &toolchain.generator {
       command.arg(format!("-G{}", generator.as_str()));
     }
-    if let Some(definitions) = toolchain.definitions.as_ref() {
-      for x in definitions {
-        command.arg(format!("-D{}={}", x.0.to_uppercase(), x.1.to_uppercase()));
-      }
+    for x in toolchain.definitions_for(self.os, self.arch, distribution)? {
+      command.arg(x.1.to_argument(&x.0, &variables));
     }
-    //println!("\n\nconfigure command: {:?}\n\n", command);
-    command.stdout(std::process::Stdio::null());
-    ensure!(command.status()?.success(), "cmake configure step failed");
+    hooks.run(HookStage::PreConfigure, &export_folder, log)?;
+    log.run(&mut command, "cmake configure")?;
 
     let mut command = std::process::Command::new("cmake");
     command
       .arg("--build")
       .arg(target_temp.clone())
       .arg("--config")
-      .arg("release")
-      .arg("--parallel");
-    //println!("\n\nbuild command: {:?}\n\n", command);
-    command.stdout(std::process::Stdio::null());
-    ensure!(command.status()?.success(), "cmake build step failed");
+      .arg(self.build_type.to_lowercase())
+      .arg("--parallel")
+      .arg(jobs.to_string())
+      .envs(self.environment.clone())
+      .envs(hooks.environment.clone());
+    log.run(&mut command, "cmake build")?;
+    hooks.run(HookStage::PostBuild, &export_folder, log)?;
 
-    let export_folder = target_temp
-      .clone()
-      .join(crate::names::EXPORT_FOLDER);
     let mut command = std::process::Command::new("cmake");
     command
       .arg("--install")
       .arg(target_temp.clone())
       .arg("--prefix")
-      .arg(export_folder.clone());
-    command.stdout(std::process::Stdio::null());
-    ensure!(command.status()?.success(), "cmake install step failed");
+      .arg(export_folder.clone())
+      .envs(self.environment.clone())
+      .envs(hooks.environment.clone());
+    log.run(&mut command, "cmake install")?;
+    hooks.run(HookStage::PostInstall, &export_folder, log)?;
 
     crate::toolchains::utl::copy_package_metafiles(
       source_directory,
@@ -94,4 +164,17 @@ impl Toolchain for CMakeToolchain
 
     Ok(export_folder)
   }
+
+  fn configuration(&self) -> String
+  {
+    let mut arguments = self.definitions
+      .iter()
+      .map(|x| format!("{}={}", x.0, x.1))
+      .collect::<Vec<_>>();
+    arguments.extend(self.environment.iter().map(|x| format!("{}={}", x.0, x.1)));
+    arguments.sort();
+    format!("cmake: {} {}", self.configure_command, arguments.join(" "))
+  }
+
+  fn build_type(&self) -> String { self.profile.configuration() }
 }
\ No newline at end of file
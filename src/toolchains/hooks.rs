@@ -0,0 +1,126 @@
+use std::path::Path;
+use std::process::Command;
+use anyhow::Context;
+use crate::builder::{BuildProfile, HooksSection, Recipe};
+use crate::core::Environment;
+use crate::toolchains::BuildLog;
+use crate::toolchains::utl::dependency_prefixes;
+use crate::types::Distribution;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HookStage
+{
+  PreConfigure,
+  PostBuild,
+  PostInstall
+}
+
+impl std::fmt::Display for HookStage {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::PreConfigure => write!(f, "pre_configure"),
+      Self::PostBuild => write!(f, "post_build"),
+      Self::PostInstall => write!(f, "post_install"),
+    }
+  }
+}
+
+/// Recipe-level environment and hooks of a single package build. Environment is applied on top of
+/// toolchain environment for every step. Hooks are run with system shell from source folder and get:
+///
+/// - `PUFF_SOURCE_DIR` - package root with unpacked sources
+/// - `PUFF_PREFIX` - export folder the package is installed into
+/// - `PUFF_ARCH`, `PUFF_OS`, `PUFF_HOST` - target architecture, operating system and GNU triple
+/// - `PUFF_DISTRIBUTION`, `PUFF_PROFILE` - distribution and build profile being built
+/// - `PUFF_DEPENDENCY_PREFIXES` - install prefixes of dependencies, separated as in `PATH`
+#[derive(Debug, Clone)]
+pub struct Hooks
+{
+  pub section: HooksSection,
+  pub environment: Vec<(String, String)>,
+  variables: Vec<(String, String)>,
+  source_directory: String
+}
+
+impl Hooks
+{
+  /// Must be created after dependencies of the package are installed
+  pub fn new(recipe: &Recipe, source_directory: &str, env: &Environment, profile: &BuildProfile, distribution: Distribution) -> anyhow::Result<Self>
+  {
+    let mut environment = recipe.env
+      .clone()
+      .unwrap_or_default()
+      .into_iter()
+      .collect::<Vec<_>>();
+    environment.sort();
+    let prefixes = std::env::join_paths(dependency_prefixes(source_directory)?)
+      .context("failed to join dependency prefixes")?;
+    Ok(Self
+    {
+      section: recipe.hooks.clone().unwrap_or_default(),
+      environment,
+      variables: vec![
+        (String::from("PUFF_SOURCE_DIR"), source_directory.to_string()),
+        (String::from("PUFF_ARCH"), env.arch.to_string()),
+        (String::from("PUFF_OS"), env.os.to_string()),
+        (String::from("PUFF_HOST"), env.host_triple()),
+        (String::from("PUFF_DISTRIBUTION"), distribution.to_string()),
+        (String::from("PUFF_PROFILE"), profile.name.clone()),
+        (String::from("PUFF_DEPENDENCY_PREFIXES"), prefixes.to_string_lossy().to_string())
+      ],
+      source_directory: source_directory.to_string()
+    })
+  }
+
+  /// Runs commands of given stage in order. `prefix` is the export folder of the package
+  pub fn run(&self, stage: HookStage, prefix: &Path, log: &BuildLog) -> anyhow::Result<()>
+  {
+    let commands = match stage {
+      HookStage::PreConfigure => &self.section.pre_configure,
+      HookStage::PostBuild => &self.section.post_build,
+      HookStage::PostInstall => &self.section.post_install
+    };
+    for x in commands.iter().flatten() {
+      let mut command = match cfg!(windows) {
+        true => Command::new("cmd"),
+        false => Command::new("sh")
+      };
+      command
+        .arg(if cfg!(windows) { "/C" } else { "-c" })
+        .arg(x)
+        .current_dir(&self.source_directory)
+        .envs(self.environment.clone())
+        .envs(self.variables.clone())
+        .env("PUFF_PREFIX", prefix);
+      log.run(&mut command, format!("{} hook: {}", stage, x).as_str())?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[cfg(unix)]
+  #[test]
+  fn test_hooks_run_with_variables() {
+    let source = tempfile::tempdir().unwrap();
+    let prefix = tempfile::tempdir().unwrap();
+    std::fs::write(prefix.path().join("test-executable"), b"").unwrap();
+    let recipe: Recipe = serde_yaml::from_str(
+      "env:\n  GREETING: hello\n\
+       hooks:\n  post_install:\n    - rm \"$PUFF_PREFIX/test-executable\"\n    - echo \"$GREETING $PUFF_DISTRIBUTION\" > out.txt\n"
+    ).unwrap();
+    let env = Environment { os: crate::types::OperatingSystem::Linux, arch: crate::types::Arch::X86_64, target: None };
+    let hooks = Hooks::new(&recipe, source.path().to_str().unwrap(), &env, &BuildProfile::default(), Distribution::Static).unwrap();
+    let log = BuildLog::new(source.path(), false).unwrap();
+
+    hooks.run(HookStage::PreConfigure, prefix.path(), &log).unwrap();
+    assert!(prefix.path().join("test-executable").exists());
+    hooks.run(HookStage::PostInstall, prefix.path(), &log).unwrap();
+    assert!(!prefix.path().join("test-executable").exists());
+    assert_eq!(std::fs::read_to_string(source.path().join("out.txt")).unwrap(), "hello static\n");
+  }
+}
@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use anyhow::{bail, Context};
+use crate::names::BUILD_LOG_FILE;
+
+/// Number of trailing log lines printed when a build step fails
+const FAILURE_TAIL_LINES: usize = 40;
+
+/// Build output of a single package. Every step appends its stdout and stderr to the log file,
+/// and in verbose mode also streams them to the terminal
+pub struct BuildLog
+{
+  pub path: PathBuf,
+  verbose: bool
+}
+
+impl BuildLog
+{
+  pub fn new(build_directory: &Path, verbose: bool) -> anyhow::Result<Self>
+  {
+    let path = build_directory.join(BUILD_LOG_FILE);
+    File::create(&path).context(format!("failed to create build log {}", path.display()))?;
+    Ok(Self { path, verbose })
+  }
+
+  /// Runs command to completion, failing with the tail of the log if it exits unsuccessfully
+  pub fn run(&self, command: &mut Command, step: &str) -> anyhow::Result<()>
+  {
+    let mut file = File::options().append(true).open(&self.path)?;
+    writeln!(file, "==> {}: {:?}", step, command)?;
+
+    let status = match self.verbose {
+      false => command
+        .stdin(Stdio::null())
+        .stdout(file.try_clone()?)
+        .stderr(file)
+        .status(),
+      true => {
+        let mut child = command
+          .stdin(Stdio::null())
+          .stdout(Stdio::piped())
+          .stderr(Stdio::piped())
+          .spawn()
+          .context(format!("failed to start {} step", step))?;
+        let file = Arc::new(Mutex::new(file));
+        let stdout = Self::tee(child.stdout.take().unwrap(), file.clone());
+        let stderr = Self::tee(child.stderr.take().unwrap(), file);
+        let status = child.wait();
+        let _ = stdout.join();
+        let _ = stderr.join();
+        status
+      }
+    }.context(format!("failed to start {} step", step))?;
+
+    if !status.success() {
+      bail!("{} step failed ({})\n{}\nfull log: {}", step, status, self.tail()?, self.path.display());
+    }
+    Ok(())
+  }
+
+  fn tee<R: std::io::Read + Send + 'static>(reader: R, file: Arc<Mutex<File>>) -> std::thread::JoinHandle<()>
+  {
+    std::thread::spawn(move || {
+      for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        println!("{}", line);
+        let _ = writeln!(file.lock().unwrap(), "{}", line);
+      }
+    })
+  }
+
+  fn tail(&self) -> anyhow::Result<String>
+  {
+    let content = std::fs::read_to_string(&self.path)?;
+    let lines = content.lines().collect::<Vec<_>>();
+    Ok(lines[lines.len().saturating_sub(FAILURE_TAIL_LINES)..].join("\n"))
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[cfg(unix)]
+  #[test]
+  fn test_failure_contains_tail() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = BuildLog::new(dir.path(), false).unwrap();
+    log.run(Command::new("sh").arg("-c").arg("echo configured"), "configure").unwrap();
+    let error = log
+      .run(Command::new("sh").arg("-c").arg("echo compiling; echo 'error: oops' >&2; exit 2"), "build")
+      .unwrap_err()
+      .to_string();
+    assert!(error.starts_with("build step failed"), "{}", error);
+    assert!(error.contains("error: oops"), "{}", error);
+    assert!(std::fs::read_to_string(&log.path).unwrap().contains("configured"));
+  }
+}
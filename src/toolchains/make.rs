@@ -0,0 +1,86 @@
+use std::env::temp_dir;
+use std::path::PathBuf;
+use anyhow::Context;
+use crate::builder::{BuildProfile, Recipe};
+use crate::core::TargetConfig;
+use crate::toolchains::{BuildLog, HookStage, Hooks, Toolchain};
+use crate::toolchains::utl::{apply_target, dependency_environment, target_configuration};
+use crate::types::Distribution;
+
+/// Toolchain for Makefile-only libraries. Install prefix is passed as both `PREFIX` and `prefix` variables
+pub struct MakeToolchain
+{
+  pub profile: BuildProfile,
+  pub target: Option<TargetConfig>,
+}
+
+impl MakeToolchain
+{
+  pub fn new(env: &crate::core::Environment, profile: &BuildProfile) -> Self
+  {
+    Self
+    {
+      profile: profile.clone(),
+      target: env.target.clone()
+    }
+  }
+}
+
+impl Toolchain for MakeToolchain
+{
+  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution, log: &BuildLog, hooks: &Hooks, jobs: usize) -> anyhow::Result<PathBuf>
+  {
+    let toolchain = recipe
+      .extract_toolchain(distribution)?
+      .make
+      .context("make toolchain was requested to build package but recipe is not configured for make")?;
+    let export_folder = temp_dir()
+      .join(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos().to_string())
+      .join(crate::names::TARGET_FOLDER)
+      .join(crate::names::EXPORT_FOLDER);
+    let mut environment = dependency_environment(source_directory, &self.profile)?;
+    apply_target(&mut environment, self.target.as_ref());
+    environment.extend(toolchain.env.clone().unwrap_or_default());
+    environment.extend(hooks.environment.clone());
+    let mut variables = toolchain.variables
+      .clone()
+      .unwrap_or_default()
+      .into_iter()
+      .map(|x| format!("{}={}", x.0, x.1))
+      .collect::<Vec<_>>();
+    variables.sort();
+    variables.push(format!("PREFIX={}", export_folder.display()));
+    variables.push(format!("prefix={}", export_folder.display()));
+    let command = || {
+      let mut command = std::process::Command::new("make");
+      command
+        .current_dir(source_directory)
+        .envs(environment.iter().map(|x| (&x.0, &x.1)));
+      command
+    };
+
+    hooks.run(HookStage::PreConfigure, &export_folder, log)?;
+    log.run(command()
+      .arg(format!("-j{}", jobs))
+      .args(toolchain.targets.clone().unwrap_or_default())
+      .args(&variables), "make")?;
+    hooks.run(HookStage::PostBuild, &export_folder, log)?;
+    log.run(command()
+      .arg("install")
+      .args(&variables), "make install")?;
+    hooks.run(HookStage::PostInstall, &export_folder, log)?;
+
+    crate::toolchains::utl::copy_package_metafiles(
+      source_directory,
+      export_folder
+        .to_str()
+        .context("failed to convert export directory path to string")?
+    )?;
+
+    Ok(export_folder)
+  }
+
+  fn configuration(&self) -> String { format!("make{}", target_configuration(self.target.as_ref())) }
+
+  fn build_type(&self) -> String { self.profile.configuration() }
+}
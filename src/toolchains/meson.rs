@@ -0,0 +1,203 @@
+use std::env::temp_dir;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use crate::builder::{BuildProfile, Recipe};
+use crate::core::TargetConfig;
+use crate::integration::pkgconfig;
+use crate::toolchains::{BuildLog, HookStage, Hooks, Toolchain};
+use crate::toolchains::utl::target_configuration;
+use crate::types::{Arch, Distribution, OperatingSystem};
+
+pub struct MesonToolchain
+{
+  pub arch: Arch,
+  pub os: OperatingSystem,
+  pub cross: bool,
+  pub build_type: String,
+  pub profile: BuildProfile,
+  pub target: Option<TargetConfig>,
+}
+
+impl MesonToolchain
+{
+  pub fn new(env: &crate::core::Environment, profile: &BuildProfile) -> Self
+  {
+    Self
+    {
+      arch: env.arch,
+      os: env.os,
+      cross: env.is_cross(),
+      build_type: match profile.build_type.to_lowercase().as_str() {
+        "debug" => "debug",
+        "relwithdebinfo" => "debugoptimized",
+        "minsizerel" => "minsize",
+        _ => "release"
+      }.to_string(),
+      profile: profile.clone(),
+      target: env.target.clone()
+    }
+  }
+
+  /// Describes puff target as meson host machine. Compilers are taken from `CC`/`CXX`/`AR`/`STRIP` of target
+  /// profile or environment
+  fn cross_file(&self, pkgconfig_directory: &Path) -> String
+  {
+    let (cpu_family, cpu) = match self.arch {
+      Arch::X86_64 => ("x86_64", "x86_64"),
+      Arch::Aarch64 | Arch::ArmV8 => ("aarch64", "armv8"),
+      Arch::Arm => ("arm", "armv6"),
+      Arch::ArmV5TE => ("arm", "armv5te"),
+      Arch::ArmV7 | Arch::ArmV7A => ("arm", "armv7a"),
+      Arch::ArmV7R => ("arm", "armv7r"),
+      Arch::Loongarch64 => ("loongarch64", "loongarch64"),
+      Arch::Unknown => ("unknown", "unknown")
+    };
+    let system = match self.os {
+      OperatingSystem::Linux => "linux",
+      OperatingSystem::Windows => "windows",
+      OperatingSystem::MacOS => "darwin",
+      OperatingSystem::Android => "android",
+      OperatingSystem::Unknown => "unknown"
+    };
+    let target = self.target.as_ref();
+    let binary = |name: &str, variable: &str, default: &str, launcher: bool| {
+      let program = target
+        .and_then(|x| x.env.get(variable).cloned())
+        .or(std::env::var(variable).ok())
+        .unwrap_or(default.to_string());
+      match target.and_then(|x| x.compiler_launcher.as_ref()).filter(|_| launcher) {
+        Some(x) => format!("{} = ['{}', '{}']\n", name, x, program),
+        None => format!("{} = '{}'\n", name, program)
+      }
+    };
+    let sysroot = target
+      .and_then(|x| x.sysroot.as_ref())
+      .map(|x| format!("sys_root = '{}'\n", x))
+      .unwrap_or_default();
+    format!(
+      "[binaries]\n{}{}{}{}pkg-config = 'pkg-config'\n\n\
+       [properties]\npkg_config_libdir = ['{}']\n{}\n\
+       [host_machine]\nsystem = '{}'\ncpu_family = '{}'\ncpu = '{}'\nendian = 'little'\n",
+      binary("c", "CC", "cc", true),
+      binary("cpp", "CXX", "c++", true),
+      binary("ar", "AR", "ar", false),
+      binary("strip", "STRIP", "strip", false),
+      pkgconfig_directory.to_string_lossy().replace('\\', "/"),
+      sysroot,
+      system,
+      cpu_family,
+      cpu
+    )
+  }
+}
+
+impl Toolchain for MesonToolchain
+{
+  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution, log: &BuildLog, hooks: &Hooks, jobs: usize) -> anyhow::Result<PathBuf>
+  {
+    let toolchain = recipe
+      .extract_toolchain(distribution)?
+      .meson
+      .context("meson toolchain was requested to build package but recipe is not configured for meson")?;
+
+    let target_temp = temp_dir()
+      .join(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos().to_string())
+      .join(crate::names::TARGET_FOLDER);
+    let export_folder = target_temp
+      .clone()
+      .join(crate::names::EXPORT_FOLDER);
+    let build_folder = target_temp.join("build");
+
+    // dependencies are fed through pkg-config
+    pkgconfig::generate(source_directory)?;
+    let pkgconfig_directory = pkgconfig::pkgconfig_directory(source_directory).canonicalize()?;
+
+    let mut command = std::process::Command::new("meson");
+    command
+      .arg("setup")
+      .arg(&build_folder)
+      .arg(source_directory)
+      .arg("--prefix")
+      .arg(&export_folder)
+      .arg("--libdir")
+      .arg("lib")
+      .arg(format!("--buildtype={}", self.build_type))
+      .arg(format!("--default-library={}", match distribution {
+        Distribution::Shared => "shared",
+        _ => "static"
+      }))
+      .arg(format!("--backend={}", toolchain.backend.as_deref().unwrap_or("ninja")))
+      .arg(format!("--pkg-config-path={}", pkgconfig_directory.display()));
+    let prefixes = crate::toolchains::utl::dependency_prefixes(source_directory)?;
+    if !prefixes.is_empty() {
+      command.arg(format!("--cmake-prefix-path={}", prefixes
+        .iter()
+        .map(|x| x.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+      ));
+    }
+    match toolchain.cross_file.as_ref() {
+      Some(x) => { command.arg("--cross-file").arg(Path::new(source_directory).join(x)); },
+      None if self.cross => {
+        std::fs::create_dir_all(&target_temp)?;
+        let cross_file = target_temp.join("puff-cross.ini");
+        std::fs::write(&cross_file, self.cross_file(&pkgconfig_directory))?;
+        command.arg("--cross-file").arg(cross_file);
+      },
+      None => ()
+    }
+    if let Some(options) = toolchain.options.as_ref() {
+      for x in options {
+        command.arg(format!("-D{}={}", x.0, x.1));
+      }
+    }
+    command.env("PKG_CONFIG_PATH", &pkgconfig_directory);
+    command.envs(self.profile.environment());
+    if let Some(target) = &self.target {
+      command.envs(&target.env);
+    }
+    command.envs(hooks.environment.clone());
+    hooks.run(HookStage::PreConfigure, &export_folder, log)?;
+    log.run(&mut command, "meson setup")?;
+
+    let mut command = std::process::Command::new("meson");
+    command
+      .arg("compile")
+      .arg("-C")
+      .arg(&build_folder)
+      .arg("-j")
+      .arg(jobs.to_string())
+      .envs(hooks.environment.clone());
+    log.run(&mut command, "meson compile")?;
+    hooks.run(HookStage::PostBuild, &export_folder, log)?;
+
+    let mut command = std::process::Command::new("meson");
+    command
+      .arg("install")
+      .arg("-C")
+      .arg(&build_folder)
+      .envs(hooks.environment.clone());
+    log.run(&mut command, "meson install")?;
+    hooks.run(HookStage::PostInstall, &export_folder, log)?;
+
+    crate::toolchains::utl::copy_package_metafiles(
+      source_directory,
+      export_folder
+        .to_str()
+        .context("failed to convert export directory path to string")?
+    )?;
+
+    Ok(export_folder)
+  }
+
+  fn configuration(&self) -> String
+  {
+    format!("meson: {}-{}{}{}", self.arch, self.os, match self.cross {
+      true => " (cross)",
+      false => ""
+    }, target_configuration(self.target.as_ref()))
+  }
+
+  fn build_type(&self) -> String { self.profile.configuration() }
+}
@@ -1,8 +1,18 @@
+mod autotools;
 mod base;
 mod cmake;
+mod hooks;
+mod log;
+mod make;
+mod meson;
 mod shell;
 pub mod utl;
 
+pub use autotools::AutotoolsToolchain;
 pub use base::Toolchain;
 pub use cmake::CMakeToolchain;
+pub use hooks::{HookStage, Hooks};
+pub use log::BuildLog;
+pub use make::MakeToolchain;
+pub use meson::MesonToolchain;
 pub use shell::ShellToolchain;
\ No newline at end of file
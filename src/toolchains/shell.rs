@@ -2,50 +2,69 @@ use std::path::PathBuf;
 use std::path::Path;
 use anyhow::{Context};
 use shlex::Shlex;
-use crate::builder::Recipe;
-use crate::toolchains::Toolchain;
+use crate::builder::{BuildProfile, Recipe};
+use crate::core::TargetConfig;
+use crate::toolchains::utl::target_configuration;
+use crate::toolchains::{BuildLog, HookStage, Hooks, Toolchain};
 use crate::types::Distribution;
 
-pub struct ShellToolchain;
+pub struct ShellToolchain
+{
+  pub profile: BuildProfile,
+  pub target: Option<TargetConfig>,
+}
 
 impl ShellToolchain
 {
-  pub fn new() -> Self { Self }
+  pub fn new(env: &crate::core::Environment, profile: &BuildProfile) -> Self
+  {
+    Self
+    {
+      profile: profile.clone(),
+      target: env.target.clone()
+    }
+  }
 }
 
 impl Toolchain for ShellToolchain
 {
-  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution) -> anyhow::Result<PathBuf>
+  fn build_from_recipe(&self, recipe: &Recipe, source_directory: &str, distribution: Distribution, log: &BuildLog, hooks: &Hooks, _jobs: usize) -> anyhow::Result<PathBuf>
   {
     let toolchain = recipe
       .extract_toolchain(distribution)?
       .shell
       .context("shell toolchain was requested to build package but recipe is not configured for shell")?;
 
+    let export_folder = Path::new(source_directory)
+      .join(crate::names::TARGET_FOLDER)
+      .join(crate::names::EXPORT_FOLDER);
+    hooks.run(HookStage::PreConfigure, &export_folder, log)?;
     for cmd in &toolchain {
       let shell_args = Shlex::new(cmd).collect::<Vec<_>>();
       let mut command = std::process::Command::new(shell_args.get(0).context("invalid shell command")?);
       command.args(&shell_args[1..]);
       command.current_dir(source_directory);
-      command
-        .output()
-        .context(format!("failed to execute shell command ({}), output: {:?}", cmd, command.output()))?;
-
-      if !command.status()?.success() {
-        return Err(anyhow::anyhow!("failed to execute shell command ({}), output: {:?}", cmd, command.output()));
+      command.envs(self.profile.environment());
+      if let Some(target) = &self.target {
+        command.envs(&target.env);
       }
+      command.envs(hooks.environment.clone());
+      log.run(&mut command, cmd)?;
     }
-
-    let target = Path::new(source_directory)
-      .join(crate::names::TARGET_FOLDER)
-      .join(crate::names::EXPORT_FOLDER);
+    // shell commands both build and install the package
+    hooks.run(HookStage::PostBuild, &export_folder, log)?;
+    hooks.run(HookStage::PostInstall, &export_folder, log)?;
     crate::toolchains::utl::copy_package_metafiles(
       source_directory,
-      target
+      export_folder
         .to_str()
         .context("failed to convert target directory path to string")?
     )?;
 
-    Ok(target)
+    Ok(export_folder)
   }
+
+  fn configuration(&self) -> String { format!("shell{}", target_configuration(self.target.as_ref())) }
+
+  fn build_type(&self) -> String { self.profile.configuration() }
 }
\ No newline at end of file
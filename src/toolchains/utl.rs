@@ -1,5 +1,30 @@
 use std::{fs, io};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use crate::builder::BuildProfile;
+use crate::core::TargetConfig;
+use crate::names::{DEPENDENCIES_FOLDER, GENERATED_FOLDER, PREFIXES_FILE};
+
+/// Folder with files generated for packages installed into `project_directory`
+pub fn generated_directory(project_directory: &str) -> PathBuf
+{
+  Path::new(project_directory)
+    .join(DEPENDENCIES_FOLDER)
+    .join(GENERATED_FOLDER)
+}
+
+/// Reads install prefixes of packages resolved for `project_directory`, in installation order
+pub fn dependency_prefixes(project_directory: &str) -> anyhow::Result<Vec<PathBuf>>
+{
+  let file = generated_directory(project_directory).join(PREFIXES_FILE);
+  if !file.exists() {
+    return Ok(Vec::new());
+  }
+  Ok(fs::read_to_string(file)?
+    .lines()
+    .filter(|x| !x.trim().is_empty())
+    .map(PathBuf::from)
+    .collect())
+}
 
 pub fn copy_package_metafiles(source_directory: &str, target_directory: &str) -> anyhow::Result<()>
 {
@@ -27,6 +52,96 @@ pub fn copy_package_metafiles(source_directory: &str, target_directory: &str) ->
   Ok(())
 }
 
+/// Compiler and pkg-config variables pointing at packages installed for `source_directory`, with flags of
+/// build profile. Flags are appended to values already present in the environment
+pub fn dependency_environment(source_directory: &str, profile: &BuildProfile) -> anyhow::Result<Vec<(String, String)>>
+{
+  let prefixes = dependency_prefixes(source_directory)?;
+  let directories = |flag: &str, folder: &str| prefixes
+    .iter()
+    .map(|x| x.join(folder))
+    .filter(|x| x.is_dir())
+    .map(|x| format!("{}{}", flag, x.display()))
+    .collect::<Vec<_>>();
+  let flags = |variable: &str, values: Vec<String>| std::env::var(variable)
+    .ok()
+    .into_iter()
+    .chain(values)
+    .collect::<Vec<_>>()
+    .join(" ");
+
+  crate::integration::pkgconfig::generate(source_directory)?;
+  Ok(vec![
+    (String::from("CFLAGS"), flags("CFLAGS", vec![profile.cflags.clone().unwrap_or(profile.compiler_flags())])),
+    (String::from("CXXFLAGS"), flags("CXXFLAGS", vec![profile.cxxflags.clone().unwrap_or(profile.compiler_flags())])),
+    (String::from("CPPFLAGS"), flags("CPPFLAGS", directories("-I", "include"))),
+    (String::from("LDFLAGS"), flags("LDFLAGS", directories("-L", "lib").into_iter().chain(profile.ldflags.clone()).collect())),
+    (String::from("PKG_CONFIG_PATH"), crate::integration::pkgconfig::pkgconfig_directory(source_directory)
+      .canonicalize()?
+      .to_string_lossy()
+      .to_string()
+    )
+  ])
+}
+
+/// Applies target profile to compiler variables: sysroot is appended to flags, compilers are wrapped with launcher
+/// and target environment variables are added last
+pub fn apply_target(environment: &mut Vec<(String, String)>, target: Option<&TargetConfig>)
+{
+  let Some(target) = target else { return; };
+  if let Some(sysroot) = &target.sysroot {
+    for x in environment.iter_mut().filter(|x| ["CFLAGS", "CXXFLAGS", "LDFLAGS"].contains(&x.0.as_str())) {
+      x.1 = format!("{} --sysroot={}", x.1, sysroot).trim().to_string();
+    }
+  }
+  if let Some(launcher) = &target.compiler_launcher {
+    for (variable, default) in [("CC", "cc"), ("CXX", "c++")] {
+      let compiler = target.env
+        .get(variable)
+        .cloned()
+        .or(std::env::var(variable).ok())
+        .unwrap_or(default.to_string());
+      environment.push((variable.to_string(), format!("{} {}", launcher, compiler)));
+    }
+  }
+  let mut variables = target.env
+    .iter()
+    .filter(|x| target.compiler_launcher.is_none() || !["CC", "CXX"].contains(&x.0.as_str()))
+    .map(|x| (x.0.clone(), x.1.clone()))
+    .collect::<Vec<_>>();
+  variables.sort();
+  environment.extend(variables);
+}
+
+/// Describes target profile for build fingerprint, empty for native builds
+pub fn target_configuration(target: Option<&TargetConfig>) -> String
+{
+  match target {
+    Some(x) => {
+      let mut variables = x.env
+        .iter()
+        .map(|y| format!("{}={}", y.0, y.1))
+        .collect::<Vec<_>>();
+      variables.sort();
+      format!(" [{} sysroot={} launcher={} {}]",
+        x.name,
+        x.sysroot.as_deref().unwrap_or_default(),
+        x.compiler_launcher.as_deref().unwrap_or_default(),
+        variables.join(" ")
+      )
+    },
+    None => String::new()
+  }
+}
+
+/// Number of cores available for builds, split between packages built concurrently
+pub fn parallel_jobs() -> usize
+{
+  std::thread::available_parallelism()
+    .map(|x| x.get())
+    .unwrap_or(1)
+}
+
 fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
   fs::create_dir_all(&dst)?;
   for entry in fs::read_dir(src)? {
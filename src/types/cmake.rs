@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde::de::{DeserializeSeed, EnumAccess, MapAccess, VariantAccess, Visitor};
+
+/// Type of CMake cache entry, as in `-D<name>:<type>=<value>`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CMakeType
+{
+  Bool,
+  String,
+  Path,
+  Filepath
+}
+
+impl std::fmt::Display for CMakeType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Bool => write!(f, "BOOL"),
+      Self::String => write!(f, "STRING"),
+      Self::Path => write!(f, "PATH"),
+      Self::Filepath => write!(f, "FILEPATH"),
+    }
+  }
+}
+
+impl FromStr for CMakeType {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.to_uppercase();
+    match s.as_str() {
+      "BOOL" => Ok(Self::Bool),
+      "STRING" => Ok(Self::String),
+      "PATH" => Ok(Self::Path),
+      "FILEPATH" => Ok(Self::Filepath),
+      _ => Err(anyhow::anyhow!("unknown cmake definition type: {}", s))
+    }
+  }
+}
+
+/// Value of CMake definition. Either plain value (`BUILD_TESTING: false`, `NAME: value`)
+/// or typed one (`{ type: PATH, value: "{deps}/zlib" }`). Values are passed to cmake verbatim
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum CMakeDefinition
+{
+  Bool(bool),
+  Typed {
+    #[serde(rename = "type")]
+    kind: CMakeType,
+    value: String
+  },
+  Value(String)
+}
+
+impl CMakeDefinition
+{
+  /// Parses `NAME=VALUE` or `NAME:TYPE=VALUE`, as accepted by `cmake -D`
+  pub fn from_argument(argument: &str) -> anyhow::Result<(String, Self)>
+  {
+    let (name, value) = argument
+      .trim()
+      .split_once('=')
+      .unwrap_or((argument.trim(), ""));
+    anyhow::ensure!(!name.is_empty(), "empty cmake definition name: {}", argument);
+    Ok(match name.split_once(':') {
+      Some((name, kind)) => (name.to_string(), Self::Typed {
+        kind: CMakeType::from_str(kind)?,
+        value: value.to_string()
+      }),
+      None => (name.to_string(), Self::Value(value.to_string()))
+    })
+  }
+
+  /// Formats `-D` argument for cmake, substituting `{name}` placeholders from `variables`
+  pub fn to_argument(&self, name: &str, variables: &HashMap<&str, String>) -> String
+  {
+    let expand = |value: &str| variables
+      .iter()
+      .fold(value.to_string(), |x, y| x.replace(&format!("{{{}}}", y.0), y.1));
+    match self {
+      Self::Bool(x) => format!("-D{}:BOOL={}", name, if *x { "ON" } else { "OFF" }),
+      Self::Typed { kind, value } => format!("-D{}:{}={}", name, kind, expand(value)),
+      Self::Value(x) => format!("-D{}={}", name, expand(x)),
+    }
+  }
+}
+
+impl std::fmt::Display for CMakeDefinition {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Bool(x) => write!(f, "{}", x),
+      Self::Typed { kind, value } => write!(f, "{}:{}", kind, value),
+      Self::Value(x) => write!(f, "{}", x),
+    }
+  }
+}
+
+struct DefinitionVisitor;
+
+impl<'de> Visitor<'de> for DefinitionVisitor
+{
+  type Value = CMakeDefinition;
+
+  fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    formatter.write_str("cmake definition value or map with type and value")
+  }
+
+  fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> { Ok(CMakeDefinition::Bool(v)) }
+  fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> { Ok(CMakeDefinition::Value(v.to_string())) }
+  fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> { Ok(CMakeDefinition::Value(v.to_string())) }
+  fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> { Ok(CMakeDefinition::Value(v.to_string())) }
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> { Ok(CMakeDefinition::Value(v.to_string())) }
+  fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> { Ok(CMakeDefinition::Value(String::new())) }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error>
+  {
+    let mut kind = None;
+    let mut value = None;
+    while let Some(key) = map.next_key::<String>()? {
+      match key.as_str() {
+        "type" => kind = Some(map.next_value::<CMakeType>()?),
+        "value" => value = Some(map.next_value::<String>()?),
+        x => return Err(de::Error::unknown_field(x, &["type", "value"]))
+      }
+    }
+    Ok(CMakeDefinition::Typed {
+      kind: kind.ok_or_else(|| de::Error::missing_field("type"))?,
+      value: value.ok_or_else(|| de::Error::missing_field("value"))?
+    })
+  }
+}
+
+impl<'de> Deserialize<'de> for CMakeDefinition {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(DefinitionVisitor)
+  }
+}
+
+/// Reads scalar definition as it is written in YAML source. Scalar is requested as enum variant name,
+/// which YAML deserializer gives unresolved, so `10.10`, `010` and `~` are not turned into numbers or null
+struct VerbatimDefinition;
+
+impl<'de> DeserializeSeed<'de> for VerbatimDefinition
+{
+  type Value = CMakeDefinition;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+    deserializer.deserialize_enum("CMakeDefinition", &[], self)
+  }
+}
+
+impl<'de> Visitor<'de> for VerbatimDefinition
+{
+  type Value = CMakeDefinition;
+
+  fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    formatter.write_str("cmake definition value")
+  }
+
+  fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error>
+  {
+    let (value, variant) = data.variant::<String>()?;
+    variant.unit_variant()?;
+    Ok(match value.as_str() {
+      "true" | "True" | "TRUE" => CMakeDefinition::Bool(true),
+      "false" | "False" | "FALSE" => CMakeDefinition::Bool(false),
+      _ => CMakeDefinition::Value(value)
+    })
+  }
+}
+
+struct DefinitionsVisitor;
+
+impl<'de> Visitor<'de> for DefinitionsVisitor
+{
+  type Value = HashMap<String, CMakeDefinition>;
+
+  fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    formatter.write_str("map of cmake definitions")
+  }
+
+  fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> { Ok(HashMap::new()) }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error>
+  {
+    let mut definitions = HashMap::new();
+    while let Some(name) = map.next_key::<String>()? {
+      // YAML deserializer only peeks at mapping before rejecting it as enum, so typed definition is read again
+      let value = match map.next_value_seed(VerbatimDefinition) {
+        Ok(x) => x,
+        Err(_) => map.next_value::<CMakeDefinition>()?
+      };
+      definitions.insert(name, value);
+    }
+    Ok(definitions)
+  }
+}
+
+/// Deserializes recipe definitions, keeping scalar values exactly as written
+pub fn deserialize_definitions<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, CMakeDefinition>, D::Error>
+{
+  deserializer.deserialize_any(DefinitionsVisitor)
+}
+
+pub fn deserialize_optional_definitions<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<HashMap<String, CMakeDefinition>>, D::Error>
+{
+  deserialize_definitions(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_definitions_are_verbatim() {
+    let definitions = deserialize_definitions(serde_yaml::Deserializer::from_str(
+      "A: false\nB: 4\nC: MyTarget\nD: { type: PATH, value: \"{deps}/Zlib\" }\n"
+    )).unwrap();
+    let variables = HashMap::from([("deps", String::from("/Work/dependencies"))]);
+    assert_eq!(definitions["A"].to_argument("A", &variables), "-DA:BOOL=OFF");
+    assert_eq!(definitions["B"].to_argument("B", &variables), "-DB=4");
+    assert_eq!(definitions["C"].to_argument("C", &variables), "-DC=MyTarget");
+    assert_eq!(definitions["D"].to_argument("D", &variables), "-DD:PATH=/Work/dependencies/Zlib");
+    assert_eq!(
+      CMakeDefinition::from_argument("X:filepath=/a/B.cmake").unwrap(),
+      (String::from("X"), CMakeDefinition::Typed { kind: CMakeType::Filepath, value: String::from("/a/B.cmake") })
+    );
+  }
+
+  #[test]
+  fn test_scalars_keep_source_text() {
+    let definitions = deserialize_definitions(serde_yaml::Deserializer::from_str(
+      "CMAKE_OSX_DEPLOYMENT_TARGET: 10.10\nVERSION: 3.10\nEMPTY: ~\nMODE: 010\nHEX: 0x1F\nON: ON\nT: { type: STRING, value: 1.50 }\n"
+    )).unwrap();
+    let variables = HashMap::new();
+    assert_eq!(definitions["CMAKE_OSX_DEPLOYMENT_TARGET"].to_argument("CMAKE_OSX_DEPLOYMENT_TARGET", &variables), "-DCMAKE_OSX_DEPLOYMENT_TARGET=10.10");
+    assert_eq!(definitions["VERSION"].to_argument("VERSION", &variables), "-DVERSION=3.10");
+    assert_eq!(definitions["EMPTY"].to_argument("EMPTY", &variables), "-DEMPTY=~");
+    assert_eq!(definitions["MODE"].to_argument("MODE", &variables), "-DMODE=010");
+    assert_eq!(definitions["HEX"].to_argument("HEX", &variables), "-DHEX=0x1F");
+    assert_eq!(definitions["ON"].to_argument("ON", &variables), "-DON=ON");
+    assert_eq!(definitions["T"].to_argument("T", &variables), "-DT:STRING=1.50");
+  }
+}
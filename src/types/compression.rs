@@ -0,0 +1,102 @@
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Hash, Default, Deserialize, Serialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression
+{
+  #[default] Gzip,
+  Zstd,
+  Xz
+}
+
+impl std::fmt::Display for Compression {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Gzip => write!(f, "gzip"),
+      Self::Zstd => write!(f, "zstd"),
+      Self::Xz => write!(f, "xz"),
+    }
+  }
+}
+
+impl FromStr for Compression {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.to_lowercase();
+    match s.as_str() {
+      "gzip" | "gz" | "tar.gz" => Ok(Self::Gzip),
+      "zstd" | "zst" | "tar.zst" => Ok(Self::Zstd),
+      "xz" | "lzma" | "tar.xz" => Ok(Self::Xz),
+      _ => Err(anyhow::anyhow!("unknown compression: {}", s))
+    }
+  }
+}
+
+impl Compression
+{
+  pub fn extension(&self) -> &'static str
+  {
+    match self {
+      Self::Gzip => "tar.gz",
+      Self::Zstd => "tar.zst",
+      Self::Xz => "tar.xz",
+    }
+  }
+
+  /// Guesses compression from file name. Only for naming purposes, use `detect` to read actual format
+  pub fn from_file_name(name: &str) -> Self
+  {
+    [Self::Zstd, Self::Xz]
+      .into_iter()
+      .find(|x| name.ends_with(x.extension()))
+      .unwrap_or_default()
+  }
+
+  pub fn from_magic(bytes: &[u8]) -> Option<Self>
+  {
+    if bytes.starts_with(&[0x1f, 0x8b]) { Some(Self::Gzip) }
+    else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) { Some(Self::Zstd) }
+    else if bytes.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]) { Some(Self::Xz) }
+    else { None }
+  }
+
+  pub fn detect(path: &Path) -> anyhow::Result<Self>
+  {
+    let mut magic = Vec::with_capacity(6);
+    std::fs::File::open(path)?
+      .take(6)
+      .read_to_end(&mut magic)?;
+    Self::from_magic(&magic).ok_or(anyhow::anyhow!("unknown archive format: {}", path.display()))
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn test_deserialize()
+  {
+    assert_eq!(Compression::from_str("gzip").unwrap(), Compression::Gzip);
+    assert_eq!(Compression::from_str("ZST").unwrap(), Compression::Zstd);
+    assert_eq!(Compression::from_str("xz").unwrap(), Compression::Xz);
+    assert!(Compression::from_str("rar").is_err());
+  }
+
+  #[test]
+  fn test_from_magic()
+  {
+    assert_eq!(Compression::from_magic(&[0x1f, 0x8b, 0x08]), Some(Compression::Gzip));
+    assert_eq!(Compression::from_magic(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]), Some(Compression::Zstd));
+    assert_eq!(Compression::from_magic(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]), Some(Compression::Xz));
+    assert_eq!(Compression::from_magic(b"ustar"), None);
+    assert_eq!(Compression::from_file_name("fmt-1.0.0-x86_64-linux-static.tar.zst"), Compression::Zstd);
+    assert_eq!(Compression::from_file_name("fmt-1.0.0-x86_64-linux-static.tar.gz"), Compression::Gzip);
+  }
+}
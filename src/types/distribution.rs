@@ -37,6 +37,18 @@ impl FromStr for Distribution {
   }
 }
 
+impl Distribution
+{
+  /// Distribution as written in package file names. Build profile other than default is appended, e.g. `static-debug`
+  pub fn with_profile(&self, profile: Option<&str>) -> String
+  {
+    match profile {
+      Some(x) => format!("{}-{}", self, x),
+      None => self.to_string()
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests
 {
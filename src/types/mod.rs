@@ -2,8 +2,14 @@ mod version;
 mod distribution;
 mod arch;
 mod os;
+mod compression;
+mod shell;
+mod cmake;
 
 pub use version::*;
 pub use distribution::*;
 pub use arch::*;
-pub use os::*;
\ No newline at end of file
+pub use os::*;
+pub use compression::*;
+pub use shell::*;
+pub use cmake::*;
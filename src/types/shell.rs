@@ -0,0 +1,54 @@
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Shell
+{
+  Bash,
+  Zsh,
+  Fish,
+  Pwsh
+}
+
+impl std::fmt::Display for Shell {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Bash => write!(f, "bash"),
+      Self::Zsh => write!(f, "zsh"),
+      Self::Fish => write!(f, "fish"),
+      Self::Pwsh => write!(f, "pwsh"),
+    }
+  }
+}
+
+impl FromStr for Shell {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.to_lowercase();
+    match s.as_str() {
+      "bash" | "sh" => Ok(Self::Bash),
+      "zsh" => Ok(Self::Zsh),
+      "fish" => Ok(Self::Fish),
+      "pwsh" | "powershell" => Ok(Self::Pwsh),
+      _ => Err(anyhow::anyhow!("unknown shell: {}", s))
+    }
+  }
+}
+
+impl Shell
+{
+  /// Guesses current shell from `SHELL` variable, falling back to powershell on Windows and bash elsewhere
+  pub fn from_env() -> Self
+  {
+    std::env::var("SHELL")
+      .ok()
+      .and_then(|x| std::path::Path::new(&x)
+        .file_stem()
+        .and_then(|y| Self::from_str(&y.to_string_lossy()).ok())
+      )
+      .unwrap_or(match cfg!(windows) {
+        true => Self::Pwsh,
+        false => Self::Bash
+      })
+  }
+}
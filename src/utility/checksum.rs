@@ -0,0 +1,18 @@
+use std::path::Path;
+use sha2::Digest;
+
+pub fn md5_file(path: &Path) -> anyhow::Result<String>
+{
+  let mut file = std::fs::File::open(path)?;
+  let mut context = md5::Context::new();
+  std::io::copy(&mut file, &mut context)?;
+  Ok(format!("{:x}", context.compute()))
+}
+
+pub fn sha256_file(path: &Path) -> anyhow::Result<String>
+{
+  let mut file = std::fs::File::open(path)?;
+  let mut hasher = sha2::Sha256::new();
+  std::io::copy(&mut file, &mut hasher)?;
+  Ok(format!("{:x}", hasher.finalize()))
+}
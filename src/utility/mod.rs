@@ -1 +1,2 @@
-pub mod ascii;
\ No newline at end of file
+pub mod ascii;
+pub mod checksum;